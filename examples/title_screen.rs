@@ -0,0 +1,46 @@
+use sabi::*;
+use bevy::{
+    prelude::*,
+    window::*,
+};
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins
+            .set(WindowPlugin {
+                primary_window: Some(Window {
+                    title: String::from("Sabi"),
+                    resolution: (1280, 800).into(),
+                    present_mode: PresentMode::AutoVsync,
+                    prevent_default_event_handling: false,
+                    ..default()
+                }),
+                ..default()
+            })
+        )
+        .insert_resource(SabiSettings::default().theme("dark"))
+        .add_plugins(SabiPlugin)
+        .add_plugins(
+            SabiMenusPlugin::new(ScriptId { chapter: "examples".into(), act: "ui".into() })
+                .title("Sabi")
+        )
+        .add_systems(Startup, setup)
+        .add_systems(Update, menu_selected)
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut user_defined_constants: ResMut<UserDefinedConstants>,
+) {
+    user_defined_constants.playername = "Test".into();
+    // Create our primary camera (which is
+    //  necessary even for 2D games)
+    commands.spawn(Camera2d::default());
+}
+
+fn menu_selected(mut msg_reader: MessageReader<TitleMenuSelected>) {
+    for TitleMenuSelected(entry) in msg_reader.read() {
+        println!("'{}' is left to the game", entry.label());
+    }
+}
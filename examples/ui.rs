@@ -20,6 +20,7 @@ fn main() {
         )
         .add_plugins(SabiPlugin)
         .add_systems(Startup, setup)
+        .add_systems(Update, quick_menu_selected)
         .run();
 }
 
@@ -27,10 +28,19 @@ fn setup(
     mut commands: Commands,
     mut msg_writer: MessageWriter<SabiStart>,
     mut user_defined_constants: ResMut<UserDefinedConstants>,
+    mut quick_menu: ResMut<QuickMenu>,
 ) {
     user_defined_constants.playername = "Test".into();
+    // Custom quick menu button, added after the built-in ones
+    let hello = commands.register_system(|| println!("Hello from the quick menu!"));
+    quick_menu.register("Hello", hello);
     // Create our primary camera (which is
     //  necessary even for 2D games)
     commands.spawn(Camera2d::default());
-    msg_writer.write(SabiStart(ScriptId { chapter: "examples".into(), act: "ui".into() }));
-}
\ No newline at end of file
+    msg_writer.write(SabiStart::new(ScriptId { chapter: "examples".into(), act: "ui".into() }));
+}
+fn quick_menu_selected(mut msg_reader: MessageReader<QuickMenuSelected>) {
+    for QuickMenuSelected(button) in msg_reader.read() {
+        println!("{:?} is left to the game", button);
+    }
+}
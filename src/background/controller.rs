@@ -1,15 +1,16 @@
-use std::collections::HashMap;
-use bevy::asset::{LoadState, LoadedFolder};
+use std::collections::HashSet;
+
 use bevy::image::TRANSPARENT_IMAGE_HANDLE;
 use bevy::prelude::*;
-use bevy::{app::{App, Plugin}, asset::{AssetServer, Handle}};
+use bevy::{app::{App, Plugin}, asset::AssetServer};
 use anyhow::Context;
 
-use crate::VisualNovelState;
-use crate::compiler::controller::{Controller, ControllerReadyMessage, ControllersSetStateMessage, SabiState, UiRoot};
-
-const BACKGROUND_Z_INDEX: i32 = 1;
-const BACKGROUNDS_ASSET_PATH: &str   = "sabi/backgrounds";
+use crate::{BackgroundChanged, SabiSettings, VisualNovelState};
+use crate::compiler::controller::{Controller, ControllerReadyMessage, ControllersSetStateMessage, LoadingProgress, SabiRunState, SabiState, SabiSystems, UiRoot};
+use crate::compiler::manifest::ActManifests;
+use crate::error::{SabiErrors, report_errors};
+use crate::loader::AssetCache;
+use crate::tween::{BlockingAnimations, CompleteAnimationsMessage, Timing, Tween};
 
 /* States */
 #[derive(States, Debug, Default, Clone, Copy, Hash, Eq, PartialEq)]
@@ -17,7 +18,7 @@ enum BackgroundControllerState {
     /// During Idle state, [BackgroundController] waits for a [ControllersSetStateMessage]
     #[default]
     Idle,
-    /// During Loading state, [BackgroundController] loads and wait for assets folder to be completely loaded
+    /// During Loading state, [BackgroundController] waits for the backgrounds of the act manifest to be loaded
     Loading,
     /// In Running state [BackgroundController] handles BackgroundChangeMessage
     Running,
@@ -36,37 +37,43 @@ impl From<SabiState> for BackgroundControllerState {
 /* Components */
 #[derive(Component)]
 pub(crate) struct BackgroundNode;
+/// Background dissolving over its [BackgroundNode] parent, which takes its image once the dissolve ends.
+/// The script waits for it when `blocking` is set
 #[derive(Component)]
-pub(crate) struct NextBackground;
+pub(crate) struct NextBackground {
+    tween: Tween,
+    blocking: bool,
+}
 
 /* Resources */
-/// Resource used to reference the [Handle] to [LoadedFolder] of backgrounds.
-#[derive(Resource)]
-struct HandleToBackgroundsFolder(Handle<LoadedFolder>);
 /// Resource to map [`Handle<Image>`] of background images to background asset names.
-#[derive(Resource)]
-struct BackgroundImages(HashMap::<String, Handle<Image>>);
+/// Backgrounds are loaded on demand, as listed by [ActManifests].
 #[derive(Resource, Default)]
-struct Dissolving(Option<f32>);
-#[derive(Resource, Default)]
-struct Sliding(BackgroundDirection);
+struct BackgroundImages(AssetCache<String, Image>);
+#[derive(Resource)]
+struct Sliding {
+    direction: BackgroundDirection,
+    tween: Tween,
+    blocking: bool,
+}
 
 /* Messages */
 /// Message used to instruct [BackgroundController] to change current background.
 #[derive(Message)]
 pub(crate) struct BackgroundChangeMessage {
     pub operation: BackgroundOperation,
+    pub timing: Timing,
 }
 
 /* Custom Types */
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub(crate) enum BackgroundOperation {
     ChangeTo(String),
     DissolveTo(Option<String>),
     SlideTo(BackgroundDirection),
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub(crate) enum BackgroundDirection {
     #[default]
     North,
@@ -79,83 +86,83 @@ pub(crate) struct BackgroundController;
 impl Plugin for BackgroundController {
     fn build(&self, app: &mut App) {
         app.add_message::<BackgroundChangeMessage>()
+            .add_message::<BackgroundChanged>()
             .init_state::<BackgroundControllerState>()
-            .init_resource::<Dissolving>()
+            .init_resource::<BackgroundImages>()
             .add_systems(Update, check_state_change)
-            .add_systems(OnEnter(BackgroundControllerState::Loading), import_backgrounds_folder)
-            .add_systems(Update, check_loading_state.run_if(in_state(BackgroundControllerState::Loading)))
+            .add_systems(OnEnter(BackgroundControllerState::Idle), clean_animations)
+            .add_systems(Update, request_backgrounds.run_if(resource_exists_and_changed::<ActManifests>))
+            .add_systems(Update, check_loading_state.pipe(report_errors).run_if(in_state(BackgroundControllerState::Loading).and(resource_exists::<ActManifests>)))
+            .add_systems(Update, update_background
+                .pipe(report_errors)
+                .in_set(SabiSystems::Stage)
+                .run_if(in_state(BackgroundControllerState::Running).and(in_state(SabiState::Running))))
             .add_systems(Update, (
-                update_background,
-                run_dissolving_animation,
-                run_sliding_animation,
-            ).run_if(in_state(BackgroundControllerState::Running)));
+                complete_animations,
+                (run_dissolving_animation.pipe(report_errors), run_sliding_animation.pipe(report_errors)),
+            ).chain().after(update_background).run_if(in_state(BackgroundControllerState::Running).and(in_state(SabiRunState::Playing))));
     }
 }
 
-/// System to check loading state of assets.
+/// Starts loading the backgrounds listed by [ActManifests], both for the current act and the ones to preload
+fn request_backgrounds(
+    asset_server: Res<AssetServer>,
+    manifests: Res<ActManifests>,
+    mut background_images: ResMut<BackgroundImages>,
+    settings: Res<SabiSettings>,
+) {
+    // Backgrounds of the previous act are released
+    let backgrounds: HashSet<&String> = manifests.backgrounds().collect();
+    background_images.0.retain(|background| backgrounds.contains(background));
+    for background in manifests.backgrounds() {
+        background_images.0.request(background, || {
+            asset_server.load(format!("{}/{}.{}", settings.backgrounds_path, background, settings.image_extension))
+        });
+    }
+}
+/// System to check loading state of the backgrounds needed by the current act.
 /// When finished, it spawns a [Node] with an empty [ImageNode] in which [BackgroundController] will spawn
 /// next backgrounds. This entity is marked with [BackgroundNode] marker
 fn check_loading_state(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    loaded_folders: Res<Assets<LoadedFolder>>,
-    folder_handle: Res<HandleToBackgroundsFolder>,
+    manifests: Res<ActManifests>,
+    mut background_images: ResMut<BackgroundImages>,
+    mut loading_progress: ResMut<LoadingProgress>,
     ui_root: Option<Single<Entity, With<UiRoot>>>,
     mut controller_state: ResMut<NextState<BackgroundControllerState>>,
     mut msg_writer: MessageWriter<ControllerReadyMessage>,
+    settings: Res<SabiSettings>,
+    mut errors: ResMut<SabiErrors>,
 ) -> Result<(), BevyError> {
-    let mut background_sprites: HashMap<String, Handle<Image>>= HashMap::new();
+    let (loaded, failed, total) = background_images.0.progress(manifests.required.backgrounds.iter(), &asset_server, &mut errors);
+    loading_progress.0.insert(Controller::Background, (loaded, failed, total));
 
-    if let Some(state) = asset_server.get_load_state(folder_handle.0.id()) {
-        match state {
-            LoadState::Loaded => {
-                if let Some(loaded_folder) = loaded_folders.get(folder_handle.0.id()) {
-                    for handle in &loaded_folder.handles {
-                        let path = handle.path()
-                            .context("Error retrieving background path")?;
-                        let filename = path.path().file_stem()
-                            .context("Background file has no name")?
-                            .to_string_lossy()
-                            .to_string();
-                        background_sprites.insert(filename, handle.clone().typed());
-                    }
-                    commands.insert_resource(BackgroundImages(background_sprites));
-                } else {
-                    return Err(anyhow::anyhow!("Could not find background loaded folder!").into());
-                }
-
-                /* Background Setup */
-                let ui_root = ui_root.context("Cannot find UiRoot node in the World")?;
-                commands.entity(ui_root.entity()).with_child((
-                    ImageNode::default(),
-                    Node {
-                        width: percent(100.),
-                        height: percent(100.),
-                        position_type: PositionType::Absolute,
-                        ..default()
-                    },
-                    Transform::default(),
-                    ZIndex(BACKGROUND_Z_INDEX),
-                    BackgroundNode,
-                    DespawnOnEnter(SabiState::Idle),
-                ));
-                controller_state.set(BackgroundControllerState::Idle);
-                msg_writer.write(ControllerReadyMessage(Controller::Background));
-                info!("background controller ready");
+    if loaded + failed == total {
+        /* Background Setup */
+        let ui_root = ui_root.context("Cannot find UiRoot node in the World")?;
+        commands.entity(ui_root.entity()).with_child((
+            ImageNode::default(),
+            Node {
+                width: percent(100.),
+                height: percent(100.),
+                position_type: PositionType::Absolute,
+                ..default()
             },
-            LoadState::Failed(e) => {
-                return Err(anyhow::anyhow!("Error loading background assets: {}", e.to_string()).into());
-            }
-            _ => {}
-        }
+            Transform::default(),
+            ZIndex(settings.layers.background),
+            BackgroundNode,
+            DespawnOnEnter(SabiState::Idle),
+        ));
+        controller_state.set(BackgroundControllerState::Idle);
+        msg_writer.write(ControllerReadyMessage(Controller::Background));
+        info!("background controller ready");
     }
     Ok(())
 }
-/// Initiate import procedure and insert [bevy::asset::LoadedFolder] handle into [HandleToBackgroundsFolder] resource.
-/// Currently only "backgrounds" folder in bevy "assets" root is supported
-fn import_backgrounds_folder(mut commands: Commands, asset_server: Res<AssetServer>){
-    let loaded_folder = asset_server.load_folder(BACKGROUNDS_ASSET_PATH);
-    commands.insert_resource(HandleToBackgroundsFolder(loaded_folder));
+/// Drops any pending animation, so that an aborted run does not leak into the next one
+fn clean_animations(mut commands: Commands) {
+    commands.remove_resource::<Sliding>();
 }
 /// Checks for state changes from main controller when in [BackgroundControllerState::Idle] state
 fn check_state_change(
@@ -171,8 +178,13 @@ fn update_background(
     mut background_change_message: MessageReader<BackgroundChangeMessage>,
     background_images: Res<BackgroundImages>,
     mut background_query: Single<(Entity, &mut ImageNode, &mut Node), With<BackgroundNode>>,
+    mut next_background_query: Query<(Entity, &mut NextBackground, &ImageNode), Without<BackgroundNode>>,
+    sliding: Option<Res<Sliding>>,
     mut vn_state: ResMut<VisualNovelState>,
+    mut animations: ResMut<BlockingAnimations>,
+    mut background_changed: MessageWriter<BackgroundChanged>,
     mut commands: Commands,
+    settings: Res<SabiSettings>,
 ) -> Result<(), BevyError> {
     for msg in background_change_message.read() {
         match &msg.operation {
@@ -184,10 +196,19 @@ fn update_background(
                 background_query.2.left = Val::Auto;
                 background_query.2.bottom = Val::Auto;
                 background_query.2.right = Val::Auto;
+                background_changed.write(BackgroundChanged { background: Some(target.clone()) });
                 info!("[ Change background to '{}']", target);
             },
             BackgroundOperation::DissolveTo(target) => {
-                commands.insert_resource(Dissolving(Some(1.)));
+                // A new dissolve starts from the end of the running one
+                for (entity, next_background, image) in next_background_query.iter_mut() {
+                    background_query.1.image = image.image.clone();
+                    background_query.1.color.set_alpha(1.);
+                    commands.entity(entity).despawn();
+                    if next_background.blocking {
+                        animations.end(&mut vn_state);
+                    }
+                }
                 let image_handle = if let Some(target) = target {
                     background_images.0.get(target)
                         .context(format!("Background '{}' does not exist", target))?
@@ -197,7 +218,7 @@ fn update_background(
                 commands.entity(background_query.0).with_child((
                     ImageNode {
                         image: image_handle.clone(),
-                        color: Color::default().with_alpha(1.),
+                        color: Color::default().with_alpha(0.),
                         ..default()
                     },
                     Node {
@@ -207,15 +228,31 @@ fn update_background(
                         ..default()
                     },
                     Transform::default(),
-                    NextBackground,
+                    NextBackground {
+                        tween: Tween::new(msg.timing, settings.animation_durations.dissolve),
+                        blocking: !msg.timing.nowait,
+                    },
                     DespawnOnExit(SabiState::Running),
                 ));
-                vn_state.blocking = true;
+                if !msg.timing.nowait {
+                    animations.start(&mut vn_state);
+                }
+                background_changed.write(BackgroundChanged { background: target.clone() });
                 info!("[ Dissolve background to '{:?}']", target);
             },
             BackgroundOperation::SlideTo(direction) => {
-                commands.insert_resource(Sliding(direction.clone()));
-                vn_state.blocking = true;
+                // The new slide replaces the running one
+                if sliding.as_ref().is_some_and(|sliding| sliding.blocking) {
+                    animations.end(&mut vn_state);
+                }
+                commands.insert_resource(Sliding {
+                    direction: direction.clone(),
+                    tween: Tween::new(msg.timing, settings.animation_durations.slide),
+                    blocking: !msg.timing.nowait,
+                });
+                if !msg.timing.nowait {
+                    animations.start(&mut vn_state);
+                }
                 info!("[ Sliding background to '{:?}']", direction);
             }
         }
@@ -223,55 +260,71 @@ fn update_background(
     Ok(())
 }
 
-/// If a valid [Dissolving] resource is present, this system runs blocks the user input and runs dissolving animation from a background to another one
+/// Jumps the running dissolve and slide to their end state
+fn complete_animations(
+    mut complete_messages: MessageReader<CompleteAnimationsMessage>,
+    mut next_background_query: Query<&mut NextBackground>,
+    sliding: Option<ResMut<Sliding>>,
+) {
+    if complete_messages.read().count() == 0 {
+        return;
+    }
+    next_background_query.iter_mut().for_each(|mut next_background| next_background.tween.finish());
+    if let Some(mut sliding) = sliding {
+        sliding.tween.finish();
+    }
+}
+
+/// Runs the dissolve of every [NextBackground] over the [BackgroundNode]
 fn run_dissolving_animation(
     mut commands: Commands,
-    mut dissolving: ResMut<Dissolving>,
     mut background_query: Single<&mut ImageNode, With<BackgroundNode>>,
-    mut next_background_query: Single<(Entity, &mut ImageNode), (With<NextBackground>, Without<BackgroundNode>)>,
+    mut next_background_query: Query<(Entity, &mut NextBackground, &mut ImageNode), Without<BackgroundNode>>,
     mut vn_state: ResMut<VisualNovelState>,
+    mut animations: ResMut<BlockingAnimations>,
+    time: Res<Time>,
 ) -> Result<(), BevyError> {
     
-    if let Some(alpha) = &mut dissolving.0 {
-        background_query.color.set_alpha(alpha.clone());
-        next_background_query.1.color.set_alpha(1. - alpha.clone());
-        *alpha -= 0.005;
-        if *alpha <= 0. {
-            commands.insert_resource(Dissolving(None));
-            background_query.image = next_background_query.1.image.clone();
+    for (entity, mut next_background, mut image) in next_background_query.iter_mut() {
+        let progress = next_background.tween.tick(time.delta_secs());
+        background_query.color.set_alpha(1. - progress);
+        image.color.set_alpha(progress);
+        if next_background.tween.is_finished() {
+            background_query.image = image.image.clone();
             background_query.color.set_alpha(1.);
-            commands.entity(next_background_query.0).despawn();
-            vn_state.blocking = false;
+            commands.entity(entity).despawn();
+            if next_background.blocking {
+                animations.end(&mut vn_state);
+            }
         }
     }
     
     Ok(())
 }
 
-/// If a [Sliding] resource is set, this system blocks the user input and runs the sliding animation of the background
+/// If a [Sliding] resource is set, this system runs the sliding animation of the background
 fn run_sliding_animation(
     mut commands: Commands,
     sliding: Option<ResMut<Sliding>>,
     mut background_query: Single<&mut Node, With<BackgroundNode>>,
     mut vn_state: ResMut<VisualNovelState>,
+    mut animations: ResMut<BlockingAnimations>,
+    time: Res<Time>,
 ) -> Result<(), BevyError> {
     
-    if let Some(sliding) = sliding {
-        vn_state.blocking = true;
-        let parameter: &mut Val = match &sliding.0 {
+    if let Some(mut sliding) = sliding {
+        let progress = sliding.tween.tick(time.delta_secs());
+        let parameter: &mut Val = match &sliding.direction {
             BackgroundDirection::North => &mut background_query.bottom,
             BackgroundDirection::East  => &mut background_query.left,
             BackgroundDirection::South => &mut background_query.top,
             BackgroundDirection::West  => &mut background_query.right,
         };
-        *parameter = match parameter {
-            Val::Percent(val) => Val::Percent(val.clone() + 0.5),
-            _ => Val::Percent(0.),
-        };
-        if let Val::Percent(val) = parameter {
-            if val.clone() > 100. {
-                commands.remove_resource::<Sliding>();
-                vn_state.blocking = false;
+        *parameter = Val::Percent(progress * 100.);
+        if sliding.tween.is_finished() {
+            commands.remove_resource::<Sliding>();
+            if sliding.blocking {
+                animations.end(&mut vn_state);
             }
         }
     }
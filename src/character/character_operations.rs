@@ -1,12 +1,8 @@
-use std::ops::Index;
 use anyhow::Context;
 use bevy::prelude::*;
-use crate::{VisualNovelState, character::{CharacterConfig, CharactersResource, controller::{CharacterPosition, FadingCharacters, MovingCharacters, SpriteKey}}, compiler::controller::SabiState};
+use crate::{VisualNovelState, character::{CharacterConfig, CharactersResource, controller::{CharacterPosition, FadingCharacters, MovingCharacter, MovingCharacters, SpriteKey}}, compiler::controller::SabiState, tween::BlockingAnimations};
 use crate::compiler::controller::UiRoot;
 
-const MOVEMENT_STEP: f32 = 0.4;
-const CHARACTERS_Z_INDEX: i32 = 3;
-
 #[derive(Component)]
 pub struct Character;
 
@@ -27,94 +23,75 @@ pub fn change_character_emotion(
    Ok(())
 }
 pub fn move_characters(
-    query: Query<(Entity, &mut Node), With<Character>>,
+    mut query: Query<&mut Node, With<Character>>,
     mut moving_characters: ResMut<MovingCharacters>,
+    mut animations: ResMut<BlockingAnimations>,
     mut game_state: ResMut<VisualNovelState>,
+    time: Res<Time>,
 ) {
-    for (entity, mut node) in query {
-        let enumerated_element = moving_characters.0.iter().enumerate().find(|(_, e)| e.0 == entity);
-        if let Some((index, target_pos)) = enumerated_element {
-            let new_value = match node.left {
-                Val::Percent(val) => {
-                    if (val - target_pos.1).abs() < MOVEMENT_STEP {
-                        target_pos.1
-                    } else if val < target_pos.1 {
-                        val + MOVEMENT_STEP
-                    } else { val - MOVEMENT_STEP }
-                },
-                _ => {
-                    warn!("Movement directives accepts only characters with percentage value as position!");
-                    moving_characters.0.remove(index);
-                    if moving_characters.0.is_empty() {
-                        game_state.blocking = false;
-                        return;
-                    }
-                    continue;
-                }
-            };
-            node.left = percent(new_value);
-            if new_value == target_pos.1 {
-                moving_characters.0.remove(index);
-            }
-            if moving_characters.0.is_empty() {
-                game_state.blocking = false;
-                return;
-            }
+    moving_characters.0.retain_mut(|moving| {
+        let running = step_movement(moving, &mut query, time.delta_secs());
+        if !running && moving.blocking {
+            animations.end(&mut game_state);
+        }
+        running
+    });
+}
+/// Advances a movement, returning whether it is still running
+fn step_movement(moving: &mut MovingCharacter, query: &mut Query<&mut Node, With<Character>>, delta: f32) -> bool {
+    // The character may have been despawned meanwhile
+    let Ok(mut node) = query.get_mut(moving.entity) else {
+        return false;
+    };
+    let from = match (moving.from, node.left) {
+        (Some(from), _) => from,
+        (None, Val::Percent(val)) => *moving.from.insert(val),
+        (None, _) => {
+            warn!("Movement directives accepts only characters with percentage value as position!");
+            return false;
         }
-    }
+    };
+    let progress = moving.tween.tick(delta);
+    node.left = percent(from + (moving.to - from) * progress);
+    !moving.tween.is_finished()
 }
 pub fn apply_alpha(
     mut commands: Commands,
     mut query: Query<&mut ImageNode, With<Character>>,
     mut fading_characters: ResMut<FadingCharacters>,
+    mut animations: ResMut<BlockingAnimations>,
     mut game_state: ResMut<VisualNovelState>,
+    time: Res<Time>,
 ) {
-    if fading_characters.0.is_empty() {
-        return;
-    }
-
-    let mut finished_anim: Vec<Entity> = Vec::new();
-    for fading_char in &fading_characters.0 {
-        let mut s = match query.get_mut(fading_char.0) {
-            Ok(e) => e,
-            Err(_) => continue
-        };
-        let mut color = s.color;
-        color.set_alpha(s.color.alpha() + fading_char.1);
-        s.color = color;
-        if color.alpha() >= 1. || color.alpha() <= 0. {
-            finished_anim.push(fading_char.0);
+    fading_characters.0.retain_mut(|fading| {
+        // The character may have been despawned meanwhile
+        let running = query.get_mut(fading.entity).is_ok_and(|mut image| {
+            let progress = fading.tween.tick(time.delta_secs());
+            image.color.set_alpha(fading.from + (fading.to - fading.from) * progress);
+            !fading.tween.is_finished()
+        });
+        if running {
+            return true;
         }
-    }
-    let mut to_remove: Vec<usize> = Vec::new();
-    fading_characters.0.iter().enumerate().for_each(|f| {
-        if finished_anim.contains(&f.1.0) {
-            to_remove.push(f.0);
+        if fading.despawn {
+            commands.entity(fading.entity).try_despawn();
         }
-    });
-    to_remove.reverse();
-    for index in to_remove {
-        let item = fading_characters.0.index(index);
-        let to_despawn = item.2;
-        if to_despawn {
-            commands.entity(item.0).despawn();
+        if fading.blocking {
+            animations.end(&mut game_state);
         }
-        fading_characters.0.remove(index);
-    }
-    if fading_characters.0.is_empty() {
-        game_state.blocking = false;
-    }
+        false
+    });
 }
 pub fn spawn_character(
     commands: &mut Commands,
     character_config: CharacterConfig,
     sprites: &Res<CharactersResource>,
-    fading: bool,
-    fading_characters: &mut ResMut<FadingCharacters>,
+    faded: bool,
     ui_root: &Single<Entity, With<UiRoot>>,
     images: &Res<Assets<Image>>,
     position: CharacterPosition,
-) -> Result<(), BevyError> {
+    z_index: i32,
+) -> Result<Entity, BevyError> {
     let sprite_key = SpriteKey {
         character: character_config.name.clone(),
         outfit: character_config.outfit.clone(),
@@ -127,7 +104,7 @@ pub fn spawn_character(
         (
             ImageNode {
                 image: image.clone(),
-                color: Color::default().with_alpha(if fading {
+                color: Color::default().with_alpha(if faded {
                     0.
                 } else { 1. }),
                 ..default()
@@ -140,15 +117,12 @@ pub fn spawn_character(
                 left: percent(position.to_percentage_value()),
                 ..default()
             },
-            ZIndex(CHARACTERS_Z_INDEX),
+            ZIndex(z_index),
             Character,
             character_config,
             DespawnOnExit(SabiState::Running)
         )
     ).id();
     commands.entity(ui_root.entity()).add_child(character_entity);
-    if fading {
-        fading_characters.0.push((character_entity, 0.01, false));
-    }
-    Ok(())
+    Ok(character_entity)
 }
@@ -1,11 +1,13 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::collections::{HashMap, HashSet};
 
 use anyhow::{Context, Result};
-use bevy::{asset::{LoadState, LoadedFolder}, prelude::*};
+use bevy::prelude::*;
 use serde::Deserialize;
 
-use crate::{VisualNovelState, character::character_operations::{apply_alpha, change_character_emotion, move_characters, spawn_character}, compiler::controller::{Controller, ControllerReadyMessage, SabiState, ControllersSetStateMessage}};
+use crate::{CharacterDespawned, CharacterSpawned, SabiSettings, VisualNovelState, character::character_operations::{apply_alpha, change_character_emotion, move_characters, spawn_character}, compiler::{controller::{Controller, ControllerReadyMessage, LoadingProgress, SabiRunState, SabiState, SabiSystems, ControllersSetStateMessage}, manifest::ActManifests}, loader::AssetCache};
 use crate::compiler::controller::UiRoot;
+use crate::error::{SabiErrors, report_errors};
+use crate::tween::{BlockingAnimations, CompleteAnimationsMessage, Timing, Tween};
 
 pub const INVISIBLE_LEFT_PERCENTAGE: f32 = -40.;
 pub const FAR_LEFT_PERCENTAGE: f32 = 5.;
@@ -14,7 +16,6 @@ pub const LEFT_PERCENTAGE: f32 = 20.;
 pub const CENTER_PERCENTAGE: f32 = 35.;
 pub const RIGHT_PERCENTAGE: f32 = 50.;
 pub const INVISIBLE_RIGHT_PERCENTAGE: f32 = 140.;
-const CHARACTERS_ASSET_PATH: &str = "sabi/characters";
 
 /* States */
 #[derive(States, Debug, Default, Clone, Copy, Hash, Eq, PartialEq)]
@@ -44,6 +45,24 @@ pub struct CharacterConfig {
     pub description: String,
     pub emotions: Vec<String>,
     pub outfits: Vec<String>,
+    /// GUI image used as textbox while the character speaks, unless the script changed it
+    #[serde(default)]
+    pub textbox: Option<String>,
+    /// Whether the textbox image is nine-sliced rather than stretched
+    #[serde(default)]
+    pub textbox_sliced: bool,
+    /// GUI image used as namebox while the character speaks, unless the script changed it
+    #[serde(default)]
+    pub namebox: Option<String>,
+    /// Hex color of the character name
+    #[serde(default)]
+    pub name_color: Option<String>,
+    /// Font of the character lines, relative to the assets root
+    #[serde(default)]
+    pub font: Option<String>,
+    /// Hex color of the character lines
+    #[serde(default)]
+    pub text_color: Option<String>,
 }
 
 #[derive(Component, Default, Debug, Clone, PartialEq)]
@@ -90,25 +109,47 @@ impl TryFrom<&str> for CharacterPosition {
 }
 
 /* Resources */
-#[derive(Resource)]
-struct HandleToCharactersFolder(Handle<LoadedFolder>);
-#[derive(Resource)]
-pub struct CharactersResource(pub CharacterSprites);
-#[derive(Resource)]
+/// Character configs loaded on demand, as listed by [ActManifests]
+#[derive(Resource, Default)]
+struct ConfigHandles(AssetCache<String, CharacterConfig>);
+/// Character sprites loaded on demand, for the outfit set in each config
+#[derive(Resource, Default)]
+pub struct CharactersResource(pub AssetCache<SpriteKey, Image>);
+#[derive(Resource, Default)]
 struct Configs(CharactersConfig);
 #[derive(Resource, Default)]
-pub struct FadingCharacters(pub Vec<(Entity, f32, bool)>); // entity, alpha_step, to_despawn
+pub struct FadingCharacters(pub Vec<FadingCharacter>);
 #[derive(Resource, Default)]
-pub struct MovingCharacters(pub Vec<(Entity, f32)>); // entity, target_position
+pub struct MovingCharacters(pub Vec<MovingCharacter>);
 
 /* Custom types */
-#[derive(Hash, Eq, PartialEq, Debug)]
+/// Character fading from the `from` alpha to the `to` one, despawned at the end when `despawn` is set.
+/// The script waits for it when `blocking` is set
+#[derive(Debug, Clone)]
+pub struct FadingCharacter {
+    pub entity: Entity,
+    pub tween: Tween,
+    pub from: f32,
+    pub to: f32,
+    pub despawn: bool,
+    pub blocking: bool,
+}
+/// Character moving to `to`, a percentage of the screen width.
+/// `from` is taken from its [Node] on the first frame of the animation
+#[derive(Debug, Clone)]
+pub struct MovingCharacter {
+    pub entity: Entity,
+    pub tween: Tween,
+    pub from: Option<f32>,
+    pub to: f32,
+    pub blocking: bool,
+}
+#[derive(Hash, Eq, PartialEq, Debug, Clone)]
 pub struct SpriteKey {
     pub character: String,
     pub outfit: String,
     pub emotion: String,
 }
-type CharacterSprites = HashMap<SpriteKey, Handle<Image>>;
 type CharactersConfig = HashMap<String, CharacterConfig>;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -138,18 +179,7 @@ pub enum CharacterOperation {
 pub struct CharacterChangeMessage {
     pub character: String,
     pub operation: CharacterOperation,
-}
-
-impl CharacterChangeMessage {
-    pub fn is_blocking(&self) -> bool {
-        match &self.operation {
-            CharacterOperation::Spawn(info) => {
-                if info.fading { true } else { false }
-            },
-            CharacterOperation::Despawn(true) => true,
-            _ => false
-        }
-    }
+    pub timing: Timing,
 }
 
 pub struct CharacterController;
@@ -158,102 +188,127 @@ impl Plugin for CharacterController {
         app.insert_resource(MovingCharacters::default())
             .insert_resource(FadingCharacters::default())
             .add_message::<CharacterChangeMessage>()
+            .add_message::<CharacterSpawned>()
+            .add_message::<CharacterDespawned>()
+            .init_resource::<ConfigHandles>()
+            .init_resource::<CharactersResource>()
+            .init_resource::<Configs>()
             .init_state::<CharacterControllerState>()
             .add_systems(Update, wait_trigger)
-            .add_systems(OnEnter(CharacterControllerState::Loading), import_characters)
-            .add_systems(Update, setup.run_if(in_state(CharacterControllerState::Loading)))
-            .add_systems(Update, (update_characters, apply_alpha, move_characters)
-                .run_if(in_state(CharacterControllerState::Running)));
+            .add_systems(OnEnter(CharacterControllerState::Idle), clean_animations)
+            .add_systems(OnEnter(CharacterControllerState::Loading), reset_configs)
+            .add_systems(Update, request_characters.run_if(resource_exists::<ActManifests>))
+            .add_systems(Update, setup.run_if(in_state(CharacterControllerState::Loading).and(resource_exists::<ActManifests>)))
+            .add_systems(Update, update_characters
+                .pipe(report_errors)
+                .in_set(SabiSystems::Stage)
+                .run_if(in_state(CharacterControllerState::Running).and(in_state(SabiState::Running))))
+            .add_systems(Update, (complete_animations, (apply_alpha, move_characters))
+                .chain()
+                .after(update_characters)
+                .run_if(in_state(CharacterControllerState::Running).and(in_state(SabiRunState::Playing))));
     }
 }
-fn define_characters_map(
-    mut commands: Commands,
-    config_res: Res<Assets<CharacterConfig>>,
-    loaded_folder: &LoadedFolder,
-) -> Result<(), BevyError> {
-    let mut characters_sprites = CharacterSprites::new();
-    let mut characters_configs = CharactersConfig::new();
-    let expected_len = PathBuf::from(CHARACTERS_ASSET_PATH).iter().count() + 3;
-    for handle in &loaded_folder.handles {
-        let path = handle
-            .path()
-            .context("Error retrieving character asset path")?
-            .path();
-        let name: String = match path.iter().nth(expected_len - 3).map(|s| s.to_string_lossy().into()) {
-            Some(name) => name,
-            None => continue,
+/// Sprites needed by a character: the emotions listed in the manifest plus the default one, in the configured outfit
+fn sprite_keys<'a>(
+    config: &'a CharacterConfig,
+    emotions: impl Iterator<Item = &'a String> + 'a,
+) -> impl Iterator<Item = SpriteKey> + 'a {
+    emotions
+        .chain(std::iter::once(&config.emotion))
+        .map(|emotion| SpriteKey {
+            character: config.name.clone(),
+            outfit: config.outfit.clone(),
+            emotion: emotion.clone(),
+        })
+}
+/// Starts loading the configs of the characters listed by [ActManifests], then their sprites once the configs are available
+fn request_characters(
+    asset_server: Res<AssetServer>,
+    manifests: Res<ActManifests>,
+    config_assets: Res<Assets<CharacterConfig>>,
+    mut config_handles: ResMut<ConfigHandles>,
+    mut sprites: ResMut<CharactersResource>,
+    mut configs: ResMut<Configs>,
+    settings: Res<SabiSettings>,
+) {
+    // Characters of the previous act are released
+    if manifests.is_changed() {
+        let names: HashSet<&String> = manifests.characters().map(|(name, _)| name).collect();
+        config_handles.0.retain(|name| names.contains(name));
+        sprites.0.retain(|key| names.contains(&key.character));
+    }
+    for (name, emotions) in manifests.characters() {
+        config_handles.0.request(name, || {
+            asset_server.load(format!("{}/{}/character.json", settings.characters_path, name))
+        });
+        let Some(config) = config_handles.0.get(name).and_then(|handle| config_assets.get(handle)) else {
+            continue;
         };
-        if path.iter().count() == expected_len {
-            let outfit = match path.iter().nth(expected_len - 2).map(|s| s.to_string_lossy().into()) {
-                Some(outfit) => outfit,
-                None => continue,
-            };
-            let emotion = match path.iter().nth(expected_len - 1) {
-                Some(os_str) => {
-                    let file = std::path::Path::new(os_str);
-                    let name = file.file_stem().map(|s| s.to_string_lossy().into_owned());
-                    if let Some(n) = name { n } else { continue }
-                }
-                None => continue,
-            };
-            let key = SpriteKey {
-                character: name,
-                outfit,
-                emotion,
-            };
-
-            characters_sprites.insert(key, handle.clone().typed());
-            
-        } else if path.iter().count() == expected_len - 1 {
-            characters_configs.insert(
-                name.clone(),
-                config_res
-                    .get(&handle.clone().typed::<CharacterConfig>())
-                    .context(format!("Failed to retrieve CharacterConfig for '{}'", name))?
-                    .clone(),
-            );
+        if !configs.0.contains_key(name) {
+            configs.0.insert(name.clone(), config.clone());
+        }
+        for key in sprite_keys(config, emotions.iter()) {
+            sprites.0.request(&key, || {
+                asset_server.load(format!("{}/{}/{}/{}.{}", settings.characters_path, key.character, key.outfit, key.emotion, settings.image_extension))
+            });
         }
     }
-    commands.insert_resource(CharactersResource(characters_sprites));
-    commands.insert_resource(Configs(characters_configs));
-    Ok(())
 }
+/// Waits for the configs and sprites needed by the current act to be loaded
 fn setup(
-    commands: Commands,
     asset_server: Res<AssetServer>,
-    loaded_folders: Res<Assets<LoadedFolder>>,
-    folder_handle: Res<HandleToCharactersFolder>,
-    configs: Res<Assets<CharacterConfig>>,
+    manifests: Res<ActManifests>,
+    config_assets: Res<Assets<CharacterConfig>>,
+    mut config_handles: ResMut<ConfigHandles>,
+    mut sprites: ResMut<CharactersResource>,
+    mut loading_progress: ResMut<LoadingProgress>,
     mut controller_state: ResMut<NextState<CharacterControllerState>>,
     mut ev_writer: MessageWriter<ControllerReadyMessage>,
-) -> Result<(), BevyError> {
-    if let Some(state) = asset_server.get_load_state(folder_handle.0.id()) {
-        match state {
-            LoadState::Loaded => {
-                if let Some(loaded_folder) = loaded_folders.get(folder_handle.0.id()) {
-                    define_characters_map(commands, configs, loaded_folder)?;
-                    ev_writer.write(ControllerReadyMessage(Controller::Character));
-                    controller_state.set(CharacterControllerState::Idle);
-                    info!("character controller ready");
-                } else {
-                    return Err(
-                        anyhow::anyhow!("Error loading character assets").into(),
-                    );
-                }
-            }
-            LoadState::Failed(e) => {
-                return Err(
-                    anyhow::anyhow!("Error loading character assets: {}", e.to_string()).into(),
-                );
-            }
-            _ => {}
-        }
+    mut errors: ResMut<SabiErrors>,
+) {
+    let required = &manifests.required.characters;
+    let (configs_loaded, configs_failed, configs_total) = config_handles.0.progress(required.keys(), &asset_server, &mut errors);
+    let keys: Vec<SpriteKey> = required.iter()
+        .filter_map(|(name, emotions)| {
+            let config = config_assets.get(config_handles.0.get(name)?)?;
+            Some(sprite_keys(config, emotions.iter()).collect::<Vec<_>>())
+        })
+        .flatten()
+        .collect();
+    let (sprites_loaded, sprites_failed, sprites_total) = sprites.0.progress(keys.iter(), &asset_server, &mut errors);
+    let progress = (configs_loaded + sprites_loaded, configs_failed + sprites_failed, configs_total + sprites_total);
+    loading_progress.0.insert(Controller::Character, progress);
+
+    // Sprites are only known once every config is loaded or failed
+    if configs_loaded + configs_failed == configs_total && sprites_loaded + sprites_failed == sprites_total {
+        ev_writer.write(ControllerReadyMessage(Controller::Character));
+        controller_state.set(CharacterControllerState::Idle);
+        info!("character controller ready");
     }
-    Ok(())
 }
-fn import_characters(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let loaded_folder = asset_server.load_folder(CHARACTERS_ASSET_PATH);
-    commands.insert_resource(HandleToCharactersFolder(loaded_folder));
+/// Drops the configs modified by the previous run, so that they are taken again from the assets
+fn reset_configs(mut configs: ResMut<Configs>) {
+    configs.0.clear();
+}
+fn clean_animations(
+    mut fading_characters: ResMut<FadingCharacters>,
+    mut moving_characters: ResMut<MovingCharacters>,
+) {
+    fading_characters.0.clear();
+    moving_characters.0.clear();
+}
+/// Moves every fading and moving character to the end of its animation
+fn complete_animations(
+    mut complete_messages: MessageReader<CompleteAnimationsMessage>,
+    mut fading_characters: ResMut<FadingCharacters>,
+    mut moving_characters: ResMut<MovingCharacters>,
+) {
+    if complete_messages.read().count() == 0 {
+        return;
+    }
+    fading_characters.0.iter_mut().for_each(|fading| fading.tween.finish());
+    moving_characters.0.iter_mut().for_each(|moving| moving.tween.finish());
 }
 fn wait_trigger(
     mut msg_reader: MessageReader<ControllersSetStateMessage>,
@@ -273,7 +328,11 @@ fn update_characters(
     mut moving_characters: ResMut<MovingCharacters>,
     mut character_change_message: MessageReader<CharacterChangeMessage>,
     mut game_state: ResMut<VisualNovelState>,
+    mut animations: ResMut<BlockingAnimations>,
+    mut character_spawned: MessageWriter<CharacterSpawned>,
+    mut character_despawned: MessageWriter<CharacterDespawned>,
     images: Res<Assets<Image>>,
+    settings: Res<SabiSettings>,
 ) -> Result<(), BevyError> {
     
     for msg in character_change_message.read() {
@@ -285,10 +344,21 @@ fn update_characters(
                 if let Some(_) = character_query.iter_mut().find(|entity| entity.1.name == character_config.name) {
                     warn!("Another instance of the character is already in the World!");
                 }
-                spawn_character(&mut commands, character_config.clone(), &sprites, info.fading, &mut fading_characters, &ui_root, &images, info.position.clone())?;
+                let entity = spawn_character(&mut commands, character_config.clone(), &sprites, info.fading, &ui_root, &images, info.position.clone(), settings.layers.characters)?;
                 if info.fading {
-                    game_state.blocking = true;
+                    fading_characters.0.push(FadingCharacter {
+                        entity,
+                        tween: Tween::new(msg.timing, settings.animation_durations.fade),
+                        from: 0.,
+                        to: 1.,
+                        despawn: false,
+                        blocking: !msg.timing.nowait,
+                    });
+                    if !msg.timing.nowait {
+                        animations.start(&mut game_state);
+                    }
                 }
+                character_spawned.write(CharacterSpawned { character: character_config.name.clone() });
             },
             CharacterOperation::EmotionChange(emotion) => {
                 if !character_config.emotions.contains(&emotion) {
@@ -306,15 +376,25 @@ fn update_characters(
             },
             CharacterOperation::Despawn(fading) => {
                 if *fading {
-                    for entity in character_query.iter().filter(|c| c.1.name == character_config.name) {
-                        fading_characters.0.push((entity.0, -0.01, true));
+                    for (entity, _, image) in character_query.iter().filter(|c| c.1.name == character_config.name) {
+                        fading_characters.0.push(FadingCharacter {
+                            entity,
+                            tween: Tween::new(msg.timing, settings.animation_durations.fade),
+                            from: image.color.alpha(),
+                            to: 0.,
+                            despawn: true,
+                            blocking: !msg.timing.nowait,
+                        });
+                        if !msg.timing.nowait {
+                            animations.start(&mut game_state);
+                        }
                     }
-                    game_state.blocking = true;
                 } else {
                     for entity in character_query.iter().filter(|c| c.1.name == character_config.name) {
                         commands.entity(entity.0).despawn();
                     }
                 }
+                character_despawned.write(CharacterDespawned { character: character_config.name.clone() });
             },
             CharacterOperation::Look(direction) => {
                 for (_, _, mut image) in character_query.iter_mut().filter(|c| c.1.name == character_config.name) {
@@ -323,9 +403,16 @@ fn update_characters(
             },
             CharacterOperation::Move(position) => {
                 for (entity, _, _) in character_query.iter_mut().filter(|c| c.1.name == character_config.name) {
-                    let target_position = position.to_percentage_value();
-                    moving_characters.0.push((entity, target_position));
-                    game_state.blocking = true;
+                    moving_characters.0.push(MovingCharacter {
+                        entity,
+                        tween: Tween::new(msg.timing, settings.animation_durations.movement),
+                        from: None,
+                        to: position.to_percentage_value(),
+                        blocking: !msg.timing.nowait,
+                    });
+                    if !msg.timing.nowait {
+                        animations.start(&mut game_state);
+                    }
                 }
             }
         }
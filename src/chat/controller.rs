@@ -1,24 +1,44 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::time::Duration;
+
 use anyhow::Context;
-use bevy::{asset::{LoadState, LoadedFolder}, prelude::*, time::Stopwatch};
+use bevy::{asset::AssetLoadFailedEvent, ecs::system::SystemId, input::keyboard::{Key, KeyboardInput}, prelude::*, time::{Stopwatch, common_conditions::on_timer}};
 use bevy_ui_widgets::{Activate, UiWidgetsPlugins};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    VisualNovelState,
-    chat::{INFOTEXT_Z_INDEX_ACTIVE, INFOTEXT_Z_INDEX_INACTIVE, ui::{
+    ActEntered, CharacterConfig, DialogueShown, SabiExportLog, SceneEntered, SabiSettings, TextInputConfirmed, UserDefinedConstants, VisualNovelState,
+    chat::{markup::Markup, scroll_text::{GUIScrollText, animate_text_effects}, theme::UiTheme, ui::{
         basic::{
-            backplate_container, infotext_container, messagetext, namebox, nametext, textbox, top_section, vn_commands
+            backplate_container, backplate_container_node, button_node, button_radius, infotext_container, infotext_node,
+            messagetext, messagetext_node, namebox, namebox_node, nametext, nametext_node, nvl_line_node, nvl_lines_node, nvl_panel,
+            nvl_panel_node, textbox, textbox_node, top_section, vn_commands
         },
-        history::history_panel
+        history::{dimmed, history_panel},
+        prompt::text_prompt
     }},
+    console::console_closed,
+    error::{SabiErrors, report_errors},
     compiler::controller::{
-        Controller, ControllerReadyMessage, ControllersSetStateMessage, SabiState, UiRoot
-    }
+        Controller, ControllerReadyMessage, ControllersSetStateMessage, LoadingProgress, SabiRunState, SabiState, SabiSystems, UiRoot
+    },
+    compiler::manifest::ActManifests,
+    loader::AssetCache,
+    preferences::{SettingsMenu, UserPreferences, close_settings_menu, settings_closed, ui::settings_menu},
+    input::{SabiAction, SabiActionMessage},
+    tween::CompleteAnimationsMessage
 };
 
-const UI_ASSET_PATH: &str = "sabi/ui";
+const READ_TEXT_FILE: &str = "read_text.json";
+/// Delay between two writes of the read lines, which are also written on scene and act changes and on exit
+const READ_TEXT_FLUSH_INTERVAL: Duration = Duration::from_secs(10);
 
 /* Messages */
+/// Written when the player clicks one of the save and load buttons of the [QuickMenu].
+/// Sabi has no save system yet, so the game handles them
+#[derive(Message, Debug, Clone)]
+pub struct QuickMenuSelected(pub QuickMenuButton);
 #[derive(Message)]
 pub(crate) struct CharacterSayMessage {
     pub name: String,
@@ -34,6 +54,16 @@ pub(crate) struct GUIChangeMessage {
     pub sprite_id: String,
     pub image_mode: GuiImageMode,
 }
+#[derive(Message)]
+pub(crate) struct NvlMessage {
+    pub operation: NvlOperation,
+}
+#[derive(Message)]
+pub(crate) struct TextInputMessage {
+    pub target: InputTarget,
+    pub prompt: String,
+    pub max_length: Option<usize>,
+}
 
 /* States */
 #[derive(States, Debug, Default, Clone, Copy, Hash, Eq, PartialEq)]
@@ -63,10 +93,6 @@ impl From<SabiState> for ChatControllerState {
 }
 
 /* Components */
-#[derive(Component, Default)]
-pub(crate) struct GUIScrollText {
-    pub message: String
-}
 #[derive(Component)]
 pub(crate) struct VNContainer;
 #[derive(Component)]
@@ -89,29 +115,265 @@ pub(crate) struct HistoryPanel;
 pub(crate) struct HistoryScrollbar;
 #[derive(Component)]
 pub(crate) struct HistoryText;
+/// Row of the history panel, holding the lowercase text matched by the search
+#[derive(Component)]
+pub(crate) struct HistoryEntry {
+    pub search: String,
+    /// Whether the row can be clicked to go back to its line, see [VisualNovelState::can_rewind_to]
+    pub rewindable: bool,
+}
+#[derive(Component)]
+pub(crate) struct HistorySpeaker;
+#[derive(Component)]
+pub(crate) struct HistoryEntryText;
+/// Search field of the history panel, holding the typed text
+#[derive(Component, Default)]
+pub(crate) struct HistorySearch(String);
+#[derive(Component)]
+pub(crate) struct NvlPanel;
+#[derive(Component)]
+pub(crate) struct NvlLines;
+#[derive(Component)]
+pub(crate) struct TextPromptPanel;
+#[derive(Component)]
+pub(crate) struct TextPromptField;
+/// Line of the NVL page, either spoken by a character or an infotext
+#[derive(Component)]
+pub(crate) struct NvlLine {
+    dialogue: bool,
+}
 
 /* Resources */
 #[derive(Resource)]
 pub(crate) struct ChatScrollStopwatch(Stopwatch);
-#[derive(Resource)]
-struct HandleToGuiFolder(Handle<LoadedFolder>);
-#[derive(Resource)]
-struct GuiImages(HashMap<String, Handle<Image>>);
+/// GUI images loaded on demand, as listed by [ActManifests]
+#[derive(Resource, Default)]
+struct GuiImages(AssetCache<String, Image>);
+/// Configs of the characters with dialogue lines, as listed by [ActManifests]
+#[derive(Resource, Default)]
+struct SpeakerConfigs(AssetCache<String, CharacterConfig>);
 #[derive(Resource)]
 pub(crate) struct CurrentTextBoxBackground(pub ImageNode);
+/// Textbox and namebox set by the script, shown for the characters without their own.
+/// A change made right before a character starts speaking is kept over their own until another character speaks
+#[derive(Resource, Default)]
+struct ScriptGui {
+    textbox: ImageNode,
+    namebox: ImageNode,
+    /// Whether the script changed the GUI since the last line
+    changed: bool,
+    speaker: Option<String>,
+}
+/// Theme of the dialogue UI, replaced once the [UiTheme] requested by the settings or a script is loaded
+#[derive(Resource, Default)]
+pub(crate) struct ActiveTheme {
+    pub theme: UiTheme,
+    handle: Option<Handle<UiTheme>>,
+}
+
+impl ActiveTheme {
+    fn request(&mut self, name: &str, asset_server: &AssetServer, settings: &SabiSettings) {
+        self.handle = Some(asset_server.load(format!("{}/{}.theme.json", settings.ui_path, name)));
+    }
+}
+/// NVL mode, where the lines stack on a full-screen page instead of replacing each other in the textbox
+#[derive(Resource, Default)]
+pub(crate) struct NvlState {
+    pub active: bool,
+    /// Whether the last line is waiting for the player
+    waiting: bool,
+    /// Lines of the page, oldest first
+    lines: Vec<Entity>,
+}
+
+impl NvlState {
+    fn clear(&mut self, commands: &mut Commands) {
+        for line in self.lines.drain(..) {
+            commands.entity(line).try_despawn();
+        }
+        self.waiting = false;
+    }
+}
+/// Text being typed in the prompt of an `input` statement, which blocks the player actions while it exists
+#[derive(Resource)]
+pub(crate) struct TextPrompt {
+    target: InputTarget,
+    value: String,
+    max_length: Option<usize>,
+}
+/// When set, the dialogue UI is hidden and holds the [VNContainer] visibility to restore
+#[derive(Resource, Default)]
+pub(crate) struct HiddenUi(pub Option<Visibility>);
+/// Buttons of the quick menu shown under the textbox and on the NVL page, from left to right.
+/// Changes are applied from the next run
+#[derive(Resource, Debug, Clone)]
+pub struct QuickMenu {
+    pub buttons: Vec<QuickMenuButton>,
+    custom: HashMap<String, SystemId>,
+}
+
+impl Default for QuickMenu {
+    /// The buttons sabi handles itself, leaving out the save and load ones until the game handles them
+    fn default() -> Self {
+        Self {
+            buttons: vec![
+                QuickMenuButton::Rewind,
+                QuickMenuButton::History,
+                QuickMenuButton::Skip,
+                QuickMenuButton::Auto,
+                QuickMenuButton::Settings,
+                QuickMenuButton::HideUi,
+            ],
+            custom: HashMap::new(),
+        }
+    }
+}
+
+impl QuickMenu {
+    /// The default buttons along with QuickSave, QuickLoad, Save and Load, which write a [QuickMenuSelected] message for the game to handle
+    pub fn with_save_buttons() -> Self {
+        let mut quick_menu = Self::default();
+        quick_menu.buttons.splice(2..2, [
+            QuickMenuButton::QuickSave,
+            QuickMenuButton::QuickLoad,
+            QuickMenuButton::Save,
+            QuickMenuButton::Load,
+        ]);
+        quick_menu
+    }
+
+    /// Adds a button labeled `label` at the end of the row, running `system` when clicked.
+    /// The system is registered with [Commands::register_system] or [World::register_system]
+    /// Labels identify the buttons, so a label which is already taken is ignored with a warning
+    pub fn register(&mut self, label: impl Into<String>, system: SystemId) -> &mut Self {
+        let label = label.into();
+        if self.custom.contains_key(&label) {
+            warn!("Quick menu button '{}' is already registered, ignoring the new one", label);
+            return self;
+        }
+        self.custom.insert(label.clone(), system);
+        self.buttons.push(QuickMenuButton::Custom(label));
+        self
+    }
+}
+/// Skip and auto modes toggled from the [QuickMenu]
+#[derive(Resource, Default)]
+pub(crate) struct ReadingModes {
+    skip: bool,
+    auto: bool,
+    /// Seconds the current line has been fully shown in auto mode
+    auto_wait: f32,
+}
+/// Lines ever shown to the player, by act, scene and statement index, stored in `read_text.json`
+/// in the [data path](SabiSettings::data_path) so that they stay read across sessions
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub(crate) struct ReadText {
+    lines: HashSet<(String, String, usize)>,
+    /// Whether the line being shown was never read before
+    #[serde(skip)]
+    current_unread: bool,
+    /// Whether lines were read since the file was last written
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl ReadText {
+    /// Reads the stored lines, starting with none when there are no valid ones
+    fn load(settings: &SabiSettings) -> Self {
+        let Some(path) = settings.data_path().map(|dir| dir.join(READ_TEXT_FILE)) else {
+            return Self::default();
+        };
+        let Ok(content) = fs::read_to_string(&path) else {
+            return Self::default();
+        };
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            warn!("Invalid read text file {}, starting with no line read: {}", path.display(), e);
+            Self::default()
+        })
+    }
+
+    fn save(&self, settings: &SabiSettings) -> anyhow::Result<()> {
+        let dir = settings.data_path().context("No user data directory to store the read lines in")?;
+        fs::create_dir_all(&dir).with_context(|| format!("Could not create {}", dir.display()))?;
+        let path = dir.join(READ_TEXT_FILE);
+        fs::write(&path, serde_json::to_string(self)?).with_context(|| format!("Could not write {}", path.display()))
+    }
+}
 
 /* Custom types */
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub(crate) enum GuiChangeTarget {
     TextBoxBackground,
     NameBoxBackground,
+    /// Switches the [UiTheme], the sprite being the theme name
+    Theme,
+}
+/// Where the text typed in an `input` prompt is stored
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum InputTarget {
+    PlayerName,
+    Variable(String),
+}
+
+impl InputTarget {
+    pub fn name(&self) -> &str {
+        match self {
+            InputTarget::PlayerName => "playername",
+            InputTarget::Variable(name) => name,
+        }
+    }
+}
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum NvlOperation {
+    /// Switches to NVL mode, on an empty page
+    Begin,
+    /// Goes back to the textbox
+    End,
+    /// Starts a new page
+    Clear,
 }
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub(crate) enum GuiImageMode {
     Sliced,
     #[default]
     Auto
 }
+/// Button of the [QuickMenu]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuickMenuButton {
+    Rewind,
+    History,
+    QuickSave,
+    QuickLoad,
+    Save,
+    Load,
+    /// Toggles the skip mode, going through the lines until an unread one unless [UserPreferences::skip_unread] is set
+    Skip,
+    /// Toggles the auto mode, going on after [UserPreferences::auto_delay] once a line is fully shown
+    Auto,
+    Settings,
+    HideUi,
+    /// Button added with [QuickMenu::register], holding its label
+    Custom(String),
+}
+
+impl From<&QuickMenuButton> for UiButtons {
+    fn from(button: &QuickMenuButton) -> Self {
+        match button {
+            QuickMenuButton::Rewind    => UiButtons::Rewind,
+            QuickMenuButton::History   => UiButtons::OpenHistory,
+            QuickMenuButton::QuickSave => UiButtons::QuickSave,
+            QuickMenuButton::QuickLoad => UiButtons::QuickLoad,
+            QuickMenuButton::Save      => UiButtons::Save,
+            QuickMenuButton::Load      => UiButtons::Load,
+            QuickMenuButton::Skip      => UiButtons::Skip,
+            QuickMenuButton::Auto      => UiButtons::Auto,
+            QuickMenuButton::Settings  => UiButtons::OpenSettings,
+            QuickMenuButton::HideUi    => UiButtons::HideUi,
+            QuickMenuButton::Custom(label) => UiButtons::Custom(label.clone()),
+        }
+    }
+}
 #[derive(Hash, Eq, PartialEq, Component, Clone, Debug)]
 pub(crate) enum UiButtons {
     OpenHistory,
@@ -119,25 +381,107 @@ pub(crate) enum UiButtons {
     Rewind,
     TextBox,
     InfoText,
+    Nvl,
+    ConfirmInput,
+    OpenSettings,
+    HideUi,
+    QuickSave,
+    QuickLoad,
+    Save,
+    Load,
+    Skip,
+    Auto,
+    /// Button of the [QuickMenu] running a system of the game
+    Custom(String),
+    /// Row of the history panel, holding its index in the history
+    HistoryEntry(usize),
+    /// Writes the log of the run from the history panel, see [SabiExportLog]
+    ExportLog,
+}
+
+impl UiButtons {
+    /// Whether the button is drawn with the button style of the [UiTheme], rather than being a clickable area
+    fn is_themed(&self) -> bool {
+        !matches!(self, UiButtons::TextBox | UiButtons::InfoText | UiButtons::Nvl | UiButtons::HistoryEntry(_))
+    }
 }
 
 pub(crate) struct ChatController;
 impl Plugin for ChatController {
     fn build(&self, app: &mut App){
+        let read_text = match app.world().get_resource::<SabiSettings>() {
+            Some(settings) => ReadText::load(settings),
+            None => ReadText::load(&SabiSettings::default()),
+        };
         app.insert_resource(ChatScrollStopwatch(Stopwatch::new()))
+            .insert_resource(read_text)
+            .init_resource::<HiddenUi>()
             .init_state::<ChatControllerState>()
             .init_state::<ChatControllerSubState>()
-            .add_systems(OnEnter(ChatControllerState::Loading), import_gui_sprites)
-            .add_systems(Update, setup.run_if(in_state(ChatControllerState::Loading)))
+            .init_resource::<GuiImages>()
+            .init_resource::<SpeakerConfigs>()
+            .init_resource::<ScriptGui>()
+            .init_resource::<NvlState>()
+            .init_resource::<ActiveTheme>()
+            .init_resource::<QuickMenu>()
+            .init_resource::<ReadingModes>()
+            .add_systems(OnEnter(ChatControllerState::Loading), request_theme)
+            .add_systems(Update, update_theme)
+            .add_systems(Update, (refresh_nodes, refresh_fonts, refresh_buttons)
+                .after(update_theme)
+                .run_if(in_state(ChatControllerState::Running).and(resource_changed::<ActiveTheme>)))
+            .add_systems(Update, refresh_nvl
+                .after(update_theme)
+                .run_if(in_state(ChatControllerState::Running).and(resource_changed::<ActiveTheme>)))
+            .add_systems(Update, request_gui_sprites
+                .run_if(resource_exists_and_changed::<ActManifests>.or(resource_exists::<ActManifests>.and(character_config_loaded))))
+            .add_systems(Update, setup.run_if(in_state(ChatControllerState::Loading).and(resource_exists::<ActManifests>)))
             .add_message::<CharacterSayMessage>()
             .add_message::<InfoTextMessage>()
             .add_message::<GUIChangeMessage>()
+            .add_message::<NvlMessage>()
+            .add_message::<TextInputMessage>()
+            .add_message::<TextInputConfirmed>()
+            .add_message::<QuickMenuSelected>()
+            .add_message::<DialogueShown>()
+            .add_message::<CompleteAnimationsMessage>()
             .add_plugins(UiWidgetsPlugins)
             .add_systems(Update, wait_trigger)
-            .add_systems(OnEnter(ChatControllerState::Running), spawn_chatbox)
-            .add_systems(Update, (update_chatbox, update_infotext, update_gui).run_if(in_state(ChatControllerState::Running)))
-            .add_observer(button_clicked_history_state)
-            .add_observer(button_clicked_default_state);
+            .add_systems(OnEnter(ChatControllerState::Running), spawn_chatbox.pipe(report_errors))
+            .add_systems(OnExit(ChatControllerState::Running), (reset_hidden_ui, reset_reading_modes))
+            .add_systems(Update, (
+                (update_gui.pipe(report_errors), update_nvl_mode, apply_speaker_style.pipe(report_errors), update_nvl, update_chatbox.pipe(report_errors)).chain(),
+                update_infotext.pipe(report_errors).after(update_nvl),
+                open_text_prompt.pipe(report_errors),
+                track_read_text,
+            ).in_set(SabiSystems::Stage).run_if(in_state(ChatControllerState::Running)))
+            // In Last, so that the AppExit written by the window systems is seen before the app closes
+            .add_systems(Last, flush_read_text.run_if(on_timer(READ_TEXT_FLUSH_INTERVAL)
+                .or(on_message::<SceneEntered>).or(on_message::<ActEntered>).or(on_message::<AppExit>)))
+            .add_systems(OnExit(ChatControllerState::Running), flush_read_text)
+            .add_systems(Update, read_text_prompt
+                .after(open_text_prompt)
+                .run_if(in_state(ChatControllerState::Running).and(resource_exists::<TextPrompt>).and(console_closed)))
+            .add_systems(OnExit(ChatControllerState::Running), close_text_prompt)
+            .add_systems(Update, read_history_search
+                .run_if(in_state(ChatControllerSubState::History).and(console_closed)))
+            .add_systems(Update, animate_text_effects.run_if(in_state(ChatControllerState::Running)))
+            // Before the settings menu closes, so that the action closing it does not open it again
+            .add_systems(Update, handle_actions.before(close_settings_menu).run_if(in_state(ChatControllerState::Running).and(in_state(SabiRunState::Playing))))
+            .add_systems(Update, run_reading_modes
+                .before(handle_actions)
+                .run_if(in_state(ChatControllerState::Running).and(in_state(SabiRunState::Playing))
+                    .and(console_closed).and(prompt_closed).and(settings_closed)))
+            .add_systems(Update, highlight_mode_buttons
+                .after(refresh_buttons)
+                .run_if(in_state(ChatControllerState::Running).and(resource_changed::<ReadingModes>.or(resource_changed::<ActiveTheme>))))
+            .add_observer(button_clicked_history_state.pipe(report_errors))
+            .add_observer(button_clicked_default_state.pipe(report_errors))
+            .add_observer(button_clicked_nvl.pipe(report_errors))
+            .add_observer(button_clicked_prompt.pipe(report_errors))
+            .add_observer(button_clicked_settings)
+            .add_observer(button_clicked_hide_ui)
+            .add_observer(button_clicked_quick_menu);
     }
 }
 fn button_clicked_history_state(
@@ -147,9 +491,15 @@ fn button_clicked_history_state(
     current_sub_state: Res<State<ChatControllerSubState>>,
     mut sub_state: ResMut<NextState<ChatControllerSubState>>,
     history_panel: Single<Entity, With<HistoryPanel>>,
+    mut message_text: Single<(Entity, &mut GUIScrollText), (With<MessageText>, Without<InfoTextComponent>)>,
+    mut info_text: Single<(Entity, &mut GUIScrollText), (With<InfoTextComponent>, Without<MessageText>)>,
+    mut game_state: ResMut<VisualNovelState>,
+    mut nvl: ResMut<NvlState>,
+    run_state: Option<Res<State<SabiRunState>>>,
+    mut export_writer: MessageWriter<SabiExportLog>,
 ) -> Result<(), BevyError> {
 
-    if *current_sub_state != ChatControllerSubState::History {
+    if *current_sub_state != ChatControllerSubState::History || !is_playing(run_state) {
         return Ok(())
     }
 
@@ -160,6 +510,20 @@ fn button_clicked_history_state(
             commands.entity(*history_panel).despawn();
             sub_state.set(ChatControllerSubState::Default);
         },
+        // The line gone back to is shown again, on a new page in NVL mode
+        UiButtons::HistoryEntry(index) if game_state.rewind_to(*index) => {
+            info!("[ Going back to history entry {} ]", index);
+            let (info_entity, info_text) = &mut *info_text;
+            info_text.clear(&mut commands, *info_entity);
+            let (message_entity, message_text) = &mut *message_text;
+            message_text.clear(&mut commands, *message_entity);
+            nvl.clear(&mut commands);
+            commands.entity(*history_panel).despawn();
+            sub_state.set(ChatControllerSubState::Default);
+        },
+        UiButtons::ExportLog => {
+            export_writer.write(SabiExportLog::default());
+        },
         _ => {}
     }
     Ok(())
@@ -169,19 +533,22 @@ fn button_clicked_default_state(
     mut commands: Commands,
     vncontainer_visibility: Single<&mut Visibility, (With<VNContainer>, Without<InfoTextContainer>, Without<InfoTextComponent>)>,
     scroll_stopwatch: ResMut<ChatScrollStopwatch>,
-    mut message_text: Single<(&mut GUIScrollText, &mut Text), (With<MessageText>, Without<NameText>, Without<InfoTextComponent>)>,
-    mut info_text: Single<(&mut GUIScrollText, &mut Text, &mut Visibility), (With<InfoTextComponent>, Without<NameText>, Without<MessageText>, Without<VNContainer>)>,
+    mut message_text: Single<(Entity, &mut GUIScrollText), (With<MessageText>, Without<NameText>, Without<InfoTextComponent>)>,
+    mut info_text: Single<(Entity, &mut GUIScrollText, &mut Visibility), (With<InfoTextComponent>, Without<NameText>, Without<MessageText>, Without<VNContainer>)>,
     info_text_container_zidx: Single<&mut ZIndex, (With<InfoTextContainer>, Without<VNContainer>)>,
     mut game_state: ResMut<VisualNovelState>,
     ui_root: Single<Entity, With<UiRoot>>,
     q_buttons: Query<(Entity, &UiButtons)>,
     current_plate: Res<CurrentTextBoxBackground>,
+    active_theme: Res<ActiveTheme>,
     asset_server: Res<AssetServer>,
     current_sub_state: Res<State<ChatControllerSubState>>,
     mut sub_state: ResMut<NextState<ChatControllerSubState>>,
+    run_state: Option<Res<State<SabiRunState>>>,
+    settings: Res<SabiSettings>,
 ) -> Result<(), BevyError> {
 
-    if *current_sub_state != ChatControllerSubState::Default {
+    if *current_sub_state != ChatControllerSubState::Default || !is_playing(run_state) {
         return Ok(())
     }
 
@@ -190,14 +557,16 @@ fn button_clicked_default_state(
     match entity.1 {
         UiButtons::OpenHistory => {
             warn!("Open history clicked");
-            let history_panel_id = commands.spawn(history_panel(current_plate, &game_state, &asset_server)?).id();
+            let history_panel_id = commands.spawn(history_panel(current_plate, &game_state, &asset_server, &settings, &active_theme.theme)?).id();
             commands.entity(*ui_root).add_child(history_panel_id);
             sub_state.set(ChatControllerSubState::History);
         },
         UiButtons::Rewind => {
             warn!("Rewind button clicked!");
-            *info_text.0 = GUIScrollText::default();
-            *message_text.0 = GUIScrollText::default();
+            let (info_entity, info_text, _) = &mut *info_text;
+            info_text.clear(&mut commands, *info_entity);
+            let (message_entity, message_text) = &mut *message_text;
+            message_text.clear(&mut commands, *message_entity);
             game_state.set_rewind();
         },
         UiButtons::TextBox => {
@@ -206,21 +575,204 @@ fn button_clicked_default_state(
         },
         UiButtons::InfoText => {
             warn!("Infotext container clicked");
-            infotext_clicked(scroll_stopwatch, info_text, info_text_container_zidx, game_state);
+            infotext_clicked(scroll_stopwatch, info_text, info_text_container_zidx, game_state, &settings);
         }
         _ => {}
     }
 
     Ok(())
 }
+/// Advances the NVL page when clicked, and drops the lines the player goes back over when rewinding
+fn button_clicked_nvl(
+    trigger: On<Activate>,
+    mut commands: Commands,
+    q_buttons: Query<&UiButtons>,
+    q_lines: Query<(&GUIScrollText, &NvlLine)>,
+    mut nvl: ResMut<NvlState>,
+    mut scroll_stopwatch: ResMut<ChatScrollStopwatch>,
+    mut game_state: ResMut<VisualNovelState>,
+    current_sub_state: Res<State<ChatControllerSubState>>,
+    run_state: Option<Res<State<SabiRunState>>>,
+) -> Result<(), BevyError> {
+    if *current_sub_state != ChatControllerSubState::Default || !is_playing(run_state) || !nvl.active {
+        return Ok(())
+    }
+
+    let button = q_buttons.get(trigger.entity)
+        .context("Clicked Entity does not have UiButtons declared")?;
+    match button {
+        UiButtons::Nvl if nvl.waiting => {
+            let Some((text, _)) = nvl.lines.last().and_then(|line| q_lines.get(*line).ok()) else {
+                return Ok(());
+            };
+            if !text.typewriter.is_finished(scroll_stopwatch.0.elapsed_secs()) {
+                // Skip line scrolling
+                scroll_stopwatch.0.set_elapsed(std::time::Duration::from_secs_f32(100000000.));
+                return Ok(());
+            }
+            info!("[ Player finished NVL line ]");
+            nvl.waiting = false;
+            game_state.blocking = false;
+        },
+        // The dialogue rewound to is shown again as a new line
+        UiButtons::Rewind if game_state.can_rewind() => {
+            if let Some(line) = nvl.lines.pop() {
+                commands.entity(line).try_despawn();
+            }
+            while let Some(line) = nvl.lines.pop() {
+                commands.entity(line).try_despawn();
+                if q_lines.get(line).is_ok_and(|(_, line)| line.dialogue) {
+                    break;
+                }
+            }
+            nvl.waiting = false;
+        },
+        _ => {}
+    }
+    Ok(())
+}
+/// Stores the text of the prompt once confirmed, letting the script go on
+fn button_clicked_prompt(
+    trigger: On<Activate>,
+    mut commands: Commands,
+    q_buttons: Query<&UiButtons>,
+    prompt: Option<Res<TextPrompt>>,
+    prompt_panel: Option<Single<Entity, With<TextPromptPanel>>>,
+    mut game_state: ResMut<VisualNovelState>,
+    mut user_defined_constants: ResMut<UserDefinedConstants>,
+    mut input_confirmed: MessageWriter<TextInputConfirmed>,
+) -> Result<(), BevyError> {
+    let button = q_buttons.get(trigger.entity)
+        .context("Clicked Entity does not have UiButtons declared")?;
+    let Some(prompt) = prompt.filter(|_| *button == UiButtons::ConfirmInput) else {
+        return Ok(());
+    };
+    let value = prompt.value.trim().to_owned();
+    if value.is_empty() {
+        return Ok(());
+    }
+
+    info!("[ Input {} set to '{}' ]", prompt.target.name(), value);
+    match &prompt.target {
+        InputTarget::PlayerName => {
+            game_state.playername = value.clone();
+            user_defined_constants.playername = value.clone();
+        },
+        InputTarget::Variable(name) => {
+            game_state.variables.insert(name.clone(), value.clone());
+        }
+    }
+    game_state.record_input(prompt.target.name(), &value);
+    input_confirmed.write(TextInputConfirmed { target: prompt.target.name().to_owned(), value });
+    if let Some(panel) = prompt_panel {
+        commands.entity(*panel).despawn();
+    }
+    commands.remove_resource::<TextPrompt>();
+    game_state.blocking = false;
+    Ok(())
+}
+/// Opens the settings menu over the dialogue UI
+fn button_clicked_settings(
+    trigger: On<Activate>,
+    mut commands: Commands,
+    q_buttons: Query<&UiButtons>,
+    preferences: Res<UserPreferences>,
+    active_theme: Res<ActiveTheme>,
+    asset_server: Res<AssetServer>,
+    settings: Res<SabiSettings>,
+    current_sub_state: Res<State<ChatControllerSubState>>,
+    run_state: Option<Res<State<SabiRunState>>>,
+    q_settings_menu: Query<(), With<SettingsMenu>>,
+) {
+    if !matches!(q_buttons.get(trigger.entity), Ok(UiButtons::OpenSettings))
+        || *current_sub_state != ChatControllerSubState::Default
+        || !is_playing(run_state)
+        || !q_settings_menu.is_empty()
+    {
+        return;
+    }
+    info!("[ Open settings ]");
+    commands.spawn(in_game_settings_menu(&preferences, &asset_server, &settings, &active_theme.theme));
+}
+/// Settings menu opened during a run, closed along with it
+fn in_game_settings_menu(preferences: &UserPreferences, asset_server: &AssetServer, settings: &SabiSettings, theme: &UiTheme) -> impl Bundle {
+    (settings_menu(preferences, asset_server, settings, theme), DespawnOnExit(SabiState::Running))
+}
+/// Hides the dialogue UI, infotext and NVL page along with their buttons until the next advancing input
+fn button_clicked_hide_ui(
+    trigger: On<Activate>,
+    q_buttons: Query<&UiButtons>,
+    mut vncontainer_visibility: Single<&mut Visibility, (With<VNContainer>, Without<InfoTextContainer>, Without<NvlPanel>)>,
+    mut infotext_visibility: Single<&mut Visibility, (With<InfoTextContainer>, Without<VNContainer>, Without<NvlPanel>)>,
+    mut nvl_visibility: Single<&mut Visibility, (With<NvlPanel>, Without<VNContainer>, Without<InfoTextContainer>)>,
+    mut hidden_ui: ResMut<HiddenUi>,
+    current_sub_state: Res<State<ChatControllerSubState>>,
+    run_state: Option<Res<State<SabiRunState>>>,
+) {
+    if !matches!(q_buttons.get(trigger.entity), Ok(UiButtons::HideUi))
+        || hidden_ui.0.is_some()
+        || *current_sub_state != ChatControllerSubState::Default
+        || !is_playing(run_state)
+    {
+        return;
+    }
+    info!("[ Hide UI ]");
+    hidden_ui.0 = Some(**vncontainer_visibility);
+    **vncontainer_visibility = Visibility::Hidden;
+    **infotext_visibility = Visibility::Hidden;
+    **nvl_visibility = Visibility::Hidden;
+}
+/// Toggles the skip and auto modes, and hands the save, load and custom buttons of the [QuickMenu] to the game
+fn button_clicked_quick_menu(
+    trigger: On<Activate>,
+    mut commands: Commands,
+    q_buttons: Query<&UiButtons>,
+    quick_menu: Res<QuickMenu>,
+    mut modes: ResMut<ReadingModes>,
+    mut selected_writer: MessageWriter<QuickMenuSelected>,
+    current_sub_state: Res<State<ChatControllerSubState>>,
+    run_state: Option<Res<State<SabiRunState>>>,
+) {
+    let Ok(button) = q_buttons.get(trigger.entity) else {
+        return;
+    };
+    if *current_sub_state != ChatControllerSubState::Default || !is_playing(run_state) {
+        return;
+    }
+    match button {
+        UiButtons::QuickSave => { selected_writer.write(QuickMenuSelected(QuickMenuButton::QuickSave)); },
+        UiButtons::QuickLoad => { selected_writer.write(QuickMenuSelected(QuickMenuButton::QuickLoad)); },
+        UiButtons::Save      => { selected_writer.write(QuickMenuSelected(QuickMenuButton::Save)); },
+        UiButtons::Load      => { selected_writer.write(QuickMenuSelected(QuickMenuButton::Load)); },
+        UiButtons::Skip => {
+            modes.skip = !modes.skip;
+            modes.auto = false;
+            info!("[ Skip mode {} ]", if modes.skip { "on" } else { "off" });
+        },
+        UiButtons::Auto => {
+            modes.auto = !modes.auto;
+            modes.skip = false;
+            modes.auto_wait = 0.;
+            info!("[ Auto mode {} ]", if modes.auto { "on" } else { "off" });
+        },
+        UiButtons::Custom(label) => match quick_menu.custom.get(label) {
+            Some(system) => commands.run_system(*system),
+            None => warn!("No system registered for the quick menu button '{}'", label),
+        },
+        _ => {}
+    }
+}
+fn is_playing(run_state: Option<Res<State<SabiRunState>>>) -> bool {
+    run_state.is_some_and(|s| *s.get() == SabiRunState::Playing)
+}
 fn infotext_clicked(
     mut scroll_stopwatch: ResMut<ChatScrollStopwatch>,
-    mut info_text: Single<(&mut GUIScrollText, &mut Text, &mut Visibility), (With<InfoTextComponent>, Without<NameText>, Without<MessageText>, Without<VNContainer>)>,
+    mut info_text: Single<(Entity, &mut GUIScrollText, &mut Visibility), (With<InfoTextComponent>, Without<NameText>, Without<MessageText>, Without<VNContainer>)>,
     mut container_zidx: Single<&mut ZIndex, (With<InfoTextContainer>, Without<VNContainer>)>,
     mut game_state: ResMut<VisualNovelState>,
+    settings: &SabiSettings,
 ) {
-    let length: u32 = (scroll_stopwatch.0.elapsed_secs() * 25.) as u32;
-    if length < info_text.0.message.len() as u32 {
+    if !info_text.1.typewriter.is_finished(scroll_stopwatch.0.elapsed_secs()) {
         // Skip message scrolling
         scroll_stopwatch.0.set_elapsed(std::time::Duration::from_secs_f32(100000000.));
         return;
@@ -230,16 +782,15 @@ fn infotext_clicked(
     // Allow transitions to be run again
     game_state.blocking = false;
     *info_text.2 = Visibility::Hidden;
-    **container_zidx = ZIndex(INFOTEXT_Z_INDEX_INACTIVE);
+    **container_zidx = ZIndex(settings.layers.infotext_inactive);
 }
 fn textbox_clicked(
     mut vncontainer_visibility: Single<&mut Visibility, (With<VNContainer>, Without<InfoTextContainer>, Without<InfoTextComponent>)>,
     mut scroll_stopwatch: ResMut<ChatScrollStopwatch>,
-    message_text: Single<(&mut GUIScrollText, &mut Text), (With<MessageText>, Without<NameText>, Without<InfoTextComponent>)>,
+    message_text: Single<(Entity, &mut GUIScrollText), (With<MessageText>, Without<NameText>, Without<InfoTextComponent>)>,
     mut game_state: ResMut<VisualNovelState>,
 ) {
-    let length: u32 = (scroll_stopwatch.0.elapsed_secs() * 50.) as u32;
-    if length < message_text.0.message.len() as u32 {
+    if !message_text.1.typewriter.is_finished(scroll_stopwatch.0.elapsed_secs()) {
         // Skip message scrolling
         scroll_stopwatch.0.set_elapsed(std::time::Duration::from_secs_f32(100000000.));
         return;
@@ -252,57 +803,220 @@ fn textbox_clicked(
     // Allow transitions to be run again
     game_state.blocking = false;
 }
+/// Waits for the GUI images and speaker configs needed by the current act to be loaded
 fn setup(
-    mut commands: Commands,
     asset_server: Res<AssetServer>,
-    loaded_folders: Res<Assets<LoadedFolder>>,
-    folder_handle: Res<HandleToGuiFolder>,
+    manifests: Res<ActManifests>,
+    config_assets: Res<Assets<CharacterConfig>>,
+    mut speaker_configs: ResMut<SpeakerConfigs>,
+    mut gui_images: ResMut<GuiImages>,
+    active_theme: Res<ActiveTheme>,
+    mut loading_progress: ResMut<LoadingProgress>,
     mut controller_state: ResMut<NextState<ChatControllerState>>,
     mut msg_writer: MessageWriter<ControllerReadyMessage>,
-) -> Result<(), BevyError> {
-    let mut gui_sprites = HashMap::<String, Handle<Image>>::new();
-    if let Some(state) = asset_server.get_load_state(folder_handle.0.id()) {
-        match state {
-            LoadState::Loaded => {
-                if let Some(loaded_folder) = loaded_folders.get(folder_handle.0.id()) {
-                    for handle in &loaded_folder.handles {
-                        let path = handle.path()
-                            .context("Error retrieving gui path")?;
-                        let filename = path.path().file_stem()
-                            .context("GUI file has no name")?
-                            .to_string_lossy()
-                            .to_string();
-                        gui_sprites.insert(filename, handle.clone().typed());
-                    }
-                } else {
-                    return Err(anyhow::anyhow!("Could not find chat loaded folder!").into());
-                }
+    mut errors: ResMut<SabiErrors>,
+) {
+    let (configs_loaded, configs_failed, configs_total) = speaker_configs.0.progress(manifests.required.speakers.iter(), &asset_server, &mut errors);
+    // Speaker sprites are only known once their configs are loaded
+    let sprites: Vec<String> = manifests.required.speakers.iter()
+        .filter_map(|name| config_assets.get(speaker_configs.0.get(name)?))
+        .flat_map(|config| config.textbox.iter().chain(config.namebox.iter()).cloned())
+        .chain(manifests.required.gui.iter().cloned())
+        .collect();
+    let (sprites_loaded, sprites_failed, sprites_total) = gui_images.0.progress(sprites.iter(), &asset_server, &mut errors);
+    let failed = configs_failed + sprites_failed;
+    let (mut loaded, mut total) = (configs_loaded + sprites_loaded, configs_total + sprites_total);
+    // A theme which failed to load is reported by update_theme and does not hold the run
+    if let Some(handle) = &active_theme.handle {
+        total += 1;
+        if !asset_server.get_load_state(handle).is_some_and(|state| state.is_loading()) {
+            loaded += 1;
+        }
+    }
+    loading_progress.0.insert(Controller::Chat, (loaded, failed, total));
 
-                commands.insert_resource(GuiImages(gui_sprites));
-                controller_state.set(ChatControllerState::Idle);
-                msg_writer.write(ControllerReadyMessage(Controller::Chat));
-                info!("chat controller ready");
-            },
-            LoadState::Failed(e) => {
-                return Err(anyhow::anyhow!("Error loading GUI assets: {}", e.to_string()).into());
-            }
-            _ => {}
+    if loaded + failed == total {
+        controller_state.set(ChatControllerState::Idle);
+        msg_writer.write(ControllerReadyMessage(Controller::Chat));
+        info!("chat controller ready");
+    }
+}
+/// Whether a character config finished loading, the textbox and namebox sprites of speakers being known from then on
+fn character_config_loaded(mut asset_events: MessageReader<AssetEvent<CharacterConfig>>) -> bool {
+    asset_events.read().filter(|event| matches!(event, AssetEvent::LoadedWithDependencies { .. })).count() > 0
+}
+/// Starts loading the GUI images and speaker configs listed by [ActManifests], both for the current act and the ones to preload,
+/// then the textboxes and nameboxes of the speakers once their configs are available
+fn request_gui_sprites(
+    asset_server: Res<AssetServer>,
+    manifests: Res<ActManifests>,
+    config_assets: Res<Assets<CharacterConfig>>,
+    mut speaker_configs: ResMut<SpeakerConfigs>,
+    mut gui_images: ResMut<GuiImages>,
+    settings: Res<SabiSettings>,
+) {
+    let load = |sprite: &String| asset_server.load(format!("{}/{}.{}", settings.ui_path, sprite, settings.image_extension));
+    // Sprites and speakers of the previous act are released
+    if manifests.is_changed() {
+        let speakers: HashSet<&String> = manifests.speakers().collect();
+        speaker_configs.0.retain(|name| speakers.contains(name));
+        let sprites: HashSet<&String> = manifests.gui()
+            .chain(speakers.iter()
+                .filter_map(|name| config_assets.get(speaker_configs.0.get(name)?))
+                .flat_map(|config| config.textbox.iter().chain(config.namebox.iter())))
+            .collect();
+        gui_images.0.retain(|sprite| sprites.contains(sprite));
+    }
+    for sprite in manifests.gui() {
+        gui_images.0.request(sprite, || load(sprite));
+    }
+    for name in manifests.speakers() {
+        speaker_configs.0.request(name, || {
+            asset_server.load(format!("{}/{}/character.json", settings.characters_path, name))
+        });
+        let Some(config) = speaker_configs.0.get(name).and_then(|handle| config_assets.get(handle)) else {
+            continue;
+        };
+        for sprite in config.textbox.iter().chain(config.namebox.iter()) {
+            gui_images.0.request(sprite, || load(sprite));
         }
     }
-    Ok(())
 }
-fn import_gui_sprites(mut commands: Commands, asset_server: Res<AssetServer> ){
-    let loaded_folder = asset_server.load_folder(UI_ASSET_PATH);
-    commands.insert_resource(HandleToGuiFolder(loaded_folder));
+/// Starts loading the theme set in [SabiSettings], going back to the default one when there is none
+fn request_theme(
+    asset_server: Res<AssetServer>,
+    mut active_theme: ResMut<ActiveTheme>,
+    settings: Res<SabiSettings>,
+) {
+    *active_theme = ActiveTheme::default();
+    if let Some(name) = &settings.theme {
+        active_theme.request(name, &asset_server, &settings);
+    }
+}
+/// Applies the requested theme once loaded, and again whenever its file changes
+fn update_theme(
+    mut asset_events: MessageReader<AssetEvent<UiTheme>>,
+    mut failed_events: MessageReader<AssetLoadFailedEvent<UiTheme>>,
+    themes: Res<Assets<UiTheme>>,
+    mut active_theme: ResMut<ActiveTheme>,
+) {
+    let Some(handle) = active_theme.handle.clone() else {
+        asset_events.clear();
+        failed_events.clear();
+        return;
+    };
+    for event in failed_events.read().filter(|e| e.id == handle.id()) {
+        warn!("Failed to load UI theme '{}', keeping the current one: {}", event.path, event.error);
+    }
+    let changed = asset_events.read().any(|event| match event {
+        AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => *id == handle.id(),
+        _ => false,
+    });
+    if changed && let Some(theme) = themes.get(&handle) {
+        info!("[ Apply UI theme '{}' ]", handle.path().map(|p| p.to_string()).unwrap_or_default());
+        active_theme.theme = theme.clone();
+    }
+}
+/// Applies the layout of the theme to the dialogue UI nodes
+fn refresh_nodes(
+    active_theme: Res<ActiveTheme>,
+    mut q_nodes: Query<
+        (&mut Node, Has<VNContainer>, Has<NameBoxBackground>, Has<NameText>, Has<TextBoxBackground>, Has<MessageText>, Has<InfoTextComponent>),
+        Or<(With<VNContainer>, With<NameBoxBackground>, With<NameText>, With<TextBoxBackground>, With<MessageText>, With<InfoTextComponent>)>
+    >,
+) {
+    let theme = &active_theme.theme;
+    for (mut node, container, namebox, nametext, textbox, messagetext, infotext) in q_nodes.iter_mut() {
+        *node = match (container, namebox, nametext, textbox, messagetext, infotext) {
+            (true, ..) => backplate_container_node(theme),
+            (_, true, ..) => namebox_node(theme),
+            (_, _, true, ..) => nametext_node(theme),
+            (_, _, _, true, ..) => textbox_node(theme),
+            (_, _, _, _, true, _) => messagetext_node(theme),
+            _ => infotext_node(theme),
+        };
+    }
+}
+/// Applies the fonts and colors of the theme, the dialogue text taking them from the next line
+fn refresh_fonts(
+    active_theme: Res<ActiveTheme>,
+    mut q_texts: Query<(
+        &mut TextFont, &mut TextColor, Has<NameText>, Has<MessageText>, Has<InfoTextComponent>, Has<HistorySpeaker>, Has<HistoryEntryText>, Option<&ChildOf>
+    )>,
+    q_buttons: Query<&UiButtons>,
+    q_entries: Query<&HistoryEntry>,
+    asset_server: Res<AssetServer>,
+    settings: Res<SabiSettings>,
+) {
+    let theme = &active_theme.theme;
+    for (mut font, mut color, nametext, messagetext, infotext, speaker, entry_text, parent) in q_texts.iter_mut() {
+        let parent = parent.map(|p| p.parent());
+        let text_theme = if nametext {
+            &theme.namebox.text
+        } else if messagetext {
+            &theme.textbox.text
+        } else if infotext {
+            &theme.infotext.text
+        } else if parent.is_some_and(|p| q_buttons.get(p).is_ok_and(UiButtons::is_themed)) {
+            &theme.buttons.text
+        } else if speaker {
+            &theme.history.speaker
+        } else if entry_text {
+            &theme.history.text
+        } else {
+            continue;
+        };
+        *font = text_theme.font(&asset_server, &settings);
+        let rewindable = parent.is_none_or(|p| q_entries.get(p).ok().is_none_or(|entry| entry.rewindable));
+        color.0 = if rewindable { text_theme.color.0 } else { dimmed(text_theme.color.0) };
+    }
+}
+/// Applies the colors and borders of the theme to the buttons
+fn refresh_buttons(
+    active_theme: Res<ActiveTheme>,
+    mut q_buttons: Query<(&UiButtons, &mut Node, &mut BorderColor, &mut BorderRadius, &mut BackgroundColor)>,
+) {
+    let theme = &active_theme.theme.buttons;
+    for (_, mut node, mut border, mut radius, mut background) in q_buttons.iter_mut().filter(|(b, ..)| b.is_themed()) {
+        *node = button_node(theme, node.position_type);
+        *border = BorderColor::all(theme.border.0);
+        *radius = button_radius(theme);
+        background.0 = theme.background.0;
+    }
+}
+/// Applies the layout and background of the theme to the NVL page, its lines keeping their fonts
+fn refresh_nvl(
+    active_theme: Res<ActiveTheme>,
+    mut q_nodes: Query<(&mut Node, Option<&mut BackgroundColor>, Has<NvlPanel>, Has<NvlLines>), Or<(With<NvlPanel>, With<NvlLines>, With<NvlLine>)>>,
+) {
+    let theme = &active_theme.theme;
+    for (mut node, background, panel, lines) in q_nodes.iter_mut() {
+        *node = match (panel, lines) {
+            (true, _) => nvl_panel_node(theme),
+            (_, true) => nvl_lines_node(theme),
+            _ => nvl_line_node(theme),
+        };
+        if panel && let Some(mut background) = background {
+            background.0 = theme.nvl.background.0;
+        }
+    }
 }
 fn spawn_chatbox(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     ui_root: Single<Entity, With<UiRoot>>,
+    active_theme: Res<ActiveTheme>,
+    mut script_gui: ResMut<ScriptGui>,
+    mut nvl: ResMut<NvlState>,
+    quick_menu: Res<QuickMenu>,
+    settings: Res<SabiSettings>,
 ) -> Result<(), BevyError> {
+    *script_gui = ScriptGui::default();
+    *nvl = NvlState::default();
+    let theme = &active_theme.theme;
     // Spawn Backplate + Nameplate
     // Container
-    let container = commands.spawn(backplate_container()).id();
+    let container = commands.spawn(backplate_container(&settings, theme)).id();
     commands.entity(ui_root.entity()).add_child(container);
 
     // Top section: Nameplate flex container
@@ -310,47 +1024,68 @@ fn spawn_chatbox(
     commands.entity(container).add_child(top_section);
 
     // Namebox Node
-    let namebox = commands.spawn(namebox()).id();
+    let namebox = commands.spawn(namebox(theme)).id();
     commands.entity(top_section).add_child(namebox);
 
     // NameText
-    let nametext = commands.spawn(nametext(&asset_server)).id();
+    let nametext = commands.spawn(nametext(&asset_server, &settings, theme)).id();
     commands.entity(namebox).add_child(nametext);
 
     // Backplate Node
-    let textbox_bg = commands.spawn(textbox()).id();
+    let textbox_bg = commands.spawn(textbox(&settings, theme)).id();
     commands.entity(container).add_child(textbox_bg);
 
     // MessageText
-    let messagetext = commands.spawn(messagetext(&asset_server)).id();
+    let messagetext = commands.spawn(messagetext(&asset_server, &settings, theme)).id();
     commands.entity(textbox_bg).add_child(messagetext);
 
     // VN commands
-    let vn_commands = commands.spawn(vn_commands()?).id();
+    let vn_commands = commands.spawn(vn_commands(&asset_server, &settings, theme, &quick_menu)?).id();
     commands.entity(textbox_bg).add_child(vn_commands);
 
     // InfoText
-    let infotext_container = commands.spawn(infotext_container(&asset_server)).id();
+    let infotext_container = commands.spawn(infotext_container(&asset_server, &settings, theme)).id();
     commands.entity(ui_root.entity()).add_child(infotext_container);
+
+    // NVL panel
+    let nvl_panel = commands.spawn(nvl_panel(&asset_server, &settings, theme, &quick_menu)?).id();
+    commands.entity(ui_root.entity()).add_child(nvl_panel);
     
     Ok(())
 }
 fn update_chatbox(
+    mut commands: Commands,
     mut event_message: MessageReader<CharacterSayMessage>,
     vncontainer_visibility: Single<&mut Visibility, With<VNContainer>>,
     mut name_text: Single<&mut Text, (With<NameText>, Without<MessageText>)>,
-    mut message_text: Single<(&mut GUIScrollText, &mut Text), (With<MessageText>, Without<NameText>)>,
+    mut message_text: Single<(Entity, &mut GUIScrollText, &TextFont, &TextColor), (With<MessageText>, Without<NameText>)>,
+    mut q_spans: Query<&mut TextSpan>,
+    asset_server: Res<AssetServer>,
     mut scroll_stopwatch: ResMut<ChatScrollStopwatch>,
     mut game_state: ResMut<VisualNovelState>,
+    mut hidden_ui: ResMut<HiddenUi>,
+    nvl: Res<NvlState>,
+    run_state: Option<Res<State<SabiRunState>>>,
+    mut dialogue_shown: MessageWriter<DialogueShown>,
+    settings: Res<SabiSettings>,
     time: Res<Time>,
 ) -> Result<(), BevyError> {
-    // Tick clock
-    let to_tick = if time.delta_secs() > 1. { std::time::Duration::from_secs_f32(0.) } else { time.delta() };
+    // Tick clock, the scrolling is paused while the ui is hidden or the run is paused
+    let to_tick = if time.delta_secs() > 1. || hidden_ui.0.is_some() || !is_playing(run_state) { std::time::Duration::from_secs_f32(0.) } else { time.delta() };
     scroll_stopwatch.0.tick(to_tick);
+    // While the ui is hidden, visibility changes are applied to the value restored later
     let mut vncontainer_visibility = vncontainer_visibility.into_inner();
+    let vncontainer_visibility = match &mut hidden_ui.0 {
+        Some(visibility) => visibility,
+        None => &mut *vncontainer_visibility,
+    };
 
     /* STANDARD SAY EVENTS INITIALIZATION [Transition::Say] */
     for ev in event_message.read() {
+        // NVL lines are shown by update_nvl
+        if nvl.active {
+            continue;
+        }
         game_state.blocking = true;
         // Make the visual novel ui container visible
         *vncontainer_visibility = Visibility::Visible;
@@ -358,9 +1093,11 @@ fn update_chatbox(
         scroll_stopwatch.0.set_elapsed(std::time::Duration::from_secs_f32(0.));
         // Update the name
         let name = if ev.name == "[_PLAYERNAME_]" { game_state.playername.clone() } else { ev.name.clone() };
-        name_text.0 = name;
+        name_text.0 = name.clone();
         println!("MESSAGE {}", ev.message);
-        message_text.0.message = ev.message.clone();
+        let (entity, font, color) = (message_text.0, message_text.2.clone(), *message_text.3);
+        message_text.1.set_text(&mut commands, entity, &ev.message, settings.text_speeds.dialogue, (&font, &color), &asset_server, &settings);
+        dialogue_shown.write(DialogueShown { character: name, text: message_text.1.markup.text.clone() });
     }
 
     // If vn container is hidden, ignore the next section dedicated to updating it
@@ -368,54 +1105,424 @@ fn update_chatbox(
         return Ok(());
     }
 
-    // Take the original string from the message object
-    let mut original_string: String = message_text.0.message.clone();
-
-    // Get the section of the string according to the elapsed time
-    let length: u32 = (scroll_stopwatch.0.elapsed_secs() * 50.) as u32;
+    // Show the glyphs revealed by the elapsed time
+    let elapsed = scroll_stopwatch.0.elapsed_secs();
+    message_text.1.show(elapsed, &mut q_spans);
 
-    // Return the section and apply it to the text object
-    original_string.truncate(length as usize);
-    message_text.1.0 = original_string;
+    // [nw] dialogues go on by themselves once shown
+    if message_text.1.markup.no_wait && message_text.1.typewriter.is_finished(elapsed) {
+        message_text.1.markup.no_wait = false;
+        *vncontainer_visibility = Visibility::Hidden;
+        game_state.blocking = false;
+    }
 
     Ok(())
 }
 fn update_infotext(
+    mut commands: Commands,
     mut event_message: MessageReader<InfoTextMessage>,
-    mut info_text: Single<(&mut GUIScrollText, &mut Text, &mut Visibility), With<InfoTextComponent>>,
+    mut info_text: Single<(Entity, &mut GUIScrollText, &mut Visibility, &TextFont, &TextColor), With<InfoTextComponent>>,
+    mut q_spans: Query<&mut TextSpan>,
+    asset_server: Res<AssetServer>,
     mut info_text_container_zidx: Single<&mut ZIndex, With<InfoTextContainer>>,
     mut scroll_stopwatch: ResMut<ChatScrollStopwatch>,
     mut game_state: ResMut<VisualNovelState>,
+    hidden_ui: Res<HiddenUi>,
+    nvl: Res<NvlState>,
+    run_state: Option<Res<State<SabiRunState>>>,
+    settings: Res<SabiSettings>,
     time: Res<Time>,
 ) -> Result<(), BevyError> {
-    // Tick clock
-    let to_tick = if time.delta_secs() > 1. { std::time::Duration::from_secs_f32(0.) } else { time.delta() };
+    // Tick clock, the scrolling is paused while the ui is hidden or the run is paused
+    let to_tick = if time.delta_secs() > 1. || hidden_ui.0.is_some() || !is_playing(run_state) { std::time::Duration::from_secs_f32(0.) } else { time.delta() };
     scroll_stopwatch.0.tick(to_tick);
 
     /* STANDARD SAY EVENTS INITIALIZATION [Transition::Say] */
     for ev in event_message.read() {
+        // NVL lines are shown by update_nvl
+        if nvl.active {
+            continue;
+        }
         game_state.blocking = true;
         // Reset the scrolling timer
         scroll_stopwatch.0.set_elapsed(std::time::Duration::from_secs_f32(0.));
         // Update the name
         println!("INFOTEXT {}", ev.text);
-        info_text.0.message = ev.text.clone();
+        let (entity, font, color) = (info_text.0, info_text.3.clone(), *info_text.4);
+        info_text.1.set_text(&mut commands, entity, &ev.text, settings.text_speeds.infotext, (&font, &color), &asset_server, &settings);
         *info_text.2 = Visibility::Visible;
-        **info_text_container_zidx = ZIndex(INFOTEXT_Z_INDEX_ACTIVE);
+        **info_text_container_zidx = ZIndex(settings.layers.infotext_active);
     }
 
-    // Take the original string from the message object
-    let mut original_string: String = info_text.0.message.clone();
+    // Show the glyphs revealed by the elapsed time
+    let elapsed = scroll_stopwatch.0.elapsed_secs();
+    info_text.1.show(elapsed, &mut q_spans);
 
-    // Get the section of the string according to the elapsed time
-    let length: u32 = (scroll_stopwatch.0.elapsed_secs() * 25.) as u32;
-
-    // Return the section and apply it to the text object
-    original_string.truncate(length as usize);
-    info_text.1.0 = original_string;
+    // [nw] infotexts go on by themselves once shown
+    if info_text.1.markup.no_wait && info_text.1.typewriter.is_finished(elapsed) {
+        info_text.1.markup.no_wait = false;
+        *info_text.2 = Visibility::Hidden;
+        **info_text_container_zidx = ZIndex(settings.layers.infotext_inactive);
+        game_state.blocking = false;
+    }
     
     Ok(())
 }
+/// Switches between the textbox and the NVL page, and clears the page
+fn update_nvl_mode(
+    mut commands: Commands,
+    mut nvl_messages: MessageReader<NvlMessage>,
+    mut nvl_visibility: Single<&mut Visibility, With<NvlPanel>>,
+    mut nvl: ResMut<NvlState>,
+    hidden_ui: Res<HiddenUi>,
+) {
+    for msg in nvl_messages.read() {
+        info!("[ NVL {:?} ]", msg.operation);
+        nvl.clear(&mut commands);
+        match msg.operation {
+            NvlOperation::Begin => nvl.active = true,
+            NvlOperation::End => nvl.active = false,
+            NvlOperation::Clear => {}
+        }
+        // The visibility is restored when the ui is shown again
+        if hidden_ui.0.is_none() {
+            **nvl_visibility = if nvl.active { Visibility::Visible } else { Visibility::Hidden };
+        }
+    }
+}
+/// Adds the dialogue and infotext lines to the NVL page while in NVL mode, the speaker name leading each dialogue line
+fn update_nvl(
+    mut commands: Commands,
+    mut say_messages: MessageReader<CharacterSayMessage>,
+    mut info_messages: MessageReader<InfoTextMessage>,
+    nvl_lines: Single<Entity, With<NvlLines>>,
+    mut q_lines: Query<&mut GUIScrollText, With<NvlLine>>,
+    mut q_spans: Query<&mut TextSpan>,
+    name_color: Single<&TextColor, With<NameText>>,
+    message_style: Single<(&TextFont, &TextColor), With<MessageText>>,
+    info_style: Single<(&TextFont, &TextColor), With<InfoTextComponent>>,
+    mut nvl: ResMut<NvlState>,
+    mut scroll_stopwatch: ResMut<ChatScrollStopwatch>,
+    mut game_state: ResMut<VisualNovelState>,
+    mut dialogue_shown: MessageWriter<DialogueShown>,
+    active_theme: Res<ActiveTheme>,
+    asset_server: Res<AssetServer>,
+    settings: Res<SabiSettings>,
+) {
+    if !nvl.active {
+        say_messages.clear();
+        info_messages.clear();
+        return;
+    }
+
+    let mut lines = Vec::new();
+    for ev in say_messages.read() {
+        let name = if ev.name == "[_PLAYERNAME_]" { game_state.playername.clone() } else { ev.name.clone() };
+        let name_hex = Srgba::from(name_color.0).to_hex();
+        let source = format!("[b][color={}]{}[/color][/b] {}", name_hex, name.replace('[', "[["), ev.message);
+        dialogue_shown.write(DialogueShown { character: name, text: Markup::parse(&ev.message).text });
+        lines.push((source, true, message_style.0.clone(), *message_style.1, settings.text_speeds.dialogue));
+    }
+    for ev in info_messages.read() {
+        lines.push((ev.text.clone(), false, info_style.0.clone(), *info_style.1, settings.text_speeds.infotext));
+    }
+
+    for (source, dialogue, font, color, speed) in lines {
+        debug!("[ NVL line ] {}", source);
+        let font = TextFont { font_size: active_theme.theme.nvl.font_size, ..font };
+        let line = commands.spawn((nvl_line_node(&active_theme.theme), font.clone(), color, NvlLine { dialogue })).id();
+        commands.entity(*nvl_lines).add_child(line);
+        let mut text = GUIScrollText::default();
+        text.set_text(&mut commands, line, &source, speed, (&font, &color), &asset_server, &settings);
+        commands.entity(line).insert(text);
+        nvl.lines.push(line);
+        nvl.waiting = true;
+        game_state.blocking = true;
+        // Reset the scrolling timer
+        scroll_stopwatch.0.set_elapsed(std::time::Duration::from_secs_f32(0.));
+    }
+
+    // Show the glyphs of the last line revealed by the elapsed time
+    let Some(mut text) = nvl.lines.last().and_then(|line| q_lines.get_mut(*line).ok()) else {
+        return;
+    };
+    let elapsed = scroll_stopwatch.0.elapsed_secs();
+    text.show(elapsed, &mut q_spans);
+
+    // [nw] lines go on by themselves once shown
+    if nvl.waiting && text.markup.no_wait && text.typewriter.is_finished(elapsed) {
+        text.markup.no_wait = false;
+        nvl.waiting = false;
+        game_state.blocking = false;
+    }
+}
+/// Shows the text prompt of an `input` statement
+fn open_text_prompt(
+    mut commands: Commands,
+    mut input_messages: MessageReader<TextInputMessage>,
+    ui_root: Single<Entity, With<UiRoot>>,
+    active_theme: Res<ActiveTheme>,
+    asset_server: Res<AssetServer>,
+    settings: Res<SabiSettings>,
+) -> Result<(), BevyError> {
+    for msg in input_messages.read() {
+        let panel = commands.spawn(text_prompt(&msg.prompt, &asset_server, &settings, &active_theme.theme)?).id();
+        commands.entity(*ui_root).add_child(panel);
+        commands.insert_resource(TextPrompt {
+            target: msg.target.clone(),
+            value: String::new(),
+            max_length: msg.max_length,
+        });
+    }
+    Ok(())
+}
+/// Edits the text of the prompt, confirming it when Enter is pressed
+fn read_text_prompt(
+    mut commands: Commands,
+    mut keyboard_messages: MessageReader<KeyboardInput>,
+    mut prompt: ResMut<TextPrompt>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    field: Option<Single<&mut Text, With<TextPromptField>>>,
+    q_buttons: Query<(Entity, &UiButtons)>,
+) {
+    for input in keyboard_messages.read() {
+        if !input.state.is_pressed() {
+            continue;
+        }
+        match &input.logical_key {
+            Key::Enter => {
+                // Once the prompt is closed, the same press must not advance the script
+                keys.clear_just_pressed(input.key_code);
+                if let Some((entity, _)) = q_buttons.iter().find(|(_, b)| **b == UiButtons::ConfirmInput) {
+                    commands.trigger(Activate { entity });
+                }
+            },
+            Key::Backspace => { prompt.value.pop(); },
+            _ => {
+                let Some(text) = &input.text else {
+                    continue;
+                };
+                for c in text.chars().filter(|c| !c.is_control()) {
+                    if prompt.max_length.is_some_and(|max| prompt.value.chars().count() >= max) {
+                        break;
+                    }
+                    prompt.value.push(c);
+                }
+            }
+        }
+    }
+    if prompt.is_changed() && let Some(mut field) = field {
+        field.0 = format!("{}|", prompt.value);
+    }
+}
+/// Filters the history entries with the text typed while the history is open
+fn read_history_search(
+    mut keyboard_messages: MessageReader<KeyboardInput>,
+    search_field: Single<(&mut Text, &mut HistorySearch)>,
+    mut q_entries: Query<(&mut Node, &HistoryEntry)>,
+) {
+    let (mut field, mut search) = search_field.into_inner();
+    for input in keyboard_messages.read().filter(|input| input.state.is_pressed()) {
+        match (&input.logical_key, &input.text) {
+            (Key::Backspace, _) => { search.0.pop(); },
+            (_, Some(text)) => search.0.extend(text.chars().filter(|c| !c.is_control())),
+            _ => {}
+        }
+    }
+    if !search.is_changed() {
+        return;
+    }
+    field.0 = if search.0.is_empty() { String::from("Search...") } else { format!("{}|", search.0) };
+    let query = search.0.to_lowercase();
+    for (mut node, entry) in q_entries.iter_mut() {
+        node.display = if entry.search.contains(&query) { Display::Flex } else { Display::None };
+    }
+}
+fn close_text_prompt(mut commands: Commands) {
+    commands.remove_resource::<TextPrompt>();
+}
+/// Run condition blocking player actions while a text prompt is being typed into
+pub(crate) fn prompt_closed(prompt: Option<Res<TextPrompt>>) -> bool {
+    prompt.is_none()
+}
+/// Applies [SabiActionMessage]s to the dialogue UI, activating the [UiButtons] bound to each action
+fn handle_actions(
+    mut commands: Commands,
+    mut action_messages: MessageReader<SabiActionMessage>,
+    q_buttons: Query<(Entity, &UiButtons)>,
+    vncontainer_visibility: Single<&mut Visibility, (With<VNContainer>, Without<InfoTextContainer>, Without<NvlPanel>)>,
+    infotext_container: Single<(&ZIndex, &mut Visibility), (With<InfoTextContainer>, Without<VNContainer>, Without<NvlPanel>)>,
+    mut nvl_visibility: Single<&mut Visibility, (With<NvlPanel>, Without<VNContainer>, Without<InfoTextContainer>)>,
+    nvl: Res<NvlState>,
+    mut hidden_ui: ResMut<HiddenUi>,
+    mut complete_animations: MessageWriter<CompleteAnimationsMessage>,
+    current_sub_state: Res<State<ChatControllerSubState>>,
+    read_text: Res<ReadText>,
+    preferences: Res<UserPreferences>,
+    settings: Res<SabiSettings>,
+    active_theme: Res<ActiveTheme>,
+    asset_server: Res<AssetServer>,
+    q_settings_menu: Query<(), With<SettingsMenu>>,
+) {
+    let mut vncontainer_visibility = vncontainer_visibility.into_inner();
+    let (infotext_zidx, mut infotext_visibility) = infotext_container.into_inner();
+    let find_button = |target: UiButtons| q_buttons.iter().find(|(_, b)| **b == target).map(|(e, _)| e);
+    let history_open = *current_sub_state == ChatControllerSubState::History;
+
+    for msg in action_messages.read() {
+        let target = match msg.0 {
+            // The settings menu closes itself on the menu action
+            _ if !q_settings_menu.is_empty() => continue,
+            SabiAction::HideUi if hidden_ui.0.is_none() && !history_open => UiButtons::HideUi,
+            // Any advancing input brings the ui back without advancing the script
+            SabiAction::HideUi | SabiAction::Advance | SabiAction::Skip if hidden_ui.0.is_some() => {
+                if let Some(visibility) = hidden_ui.0.take() {
+                    *vncontainer_visibility = visibility;
+                }
+                *infotext_visibility = Visibility::Inherited;
+                if nvl.active {
+                    **nvl_visibility = Visibility::Visible;
+                }
+                continue;
+            },
+            _ if hidden_ui.0.is_some() => continue,
+            SabiAction::Skip if read_text.current_unread && !preferences.skip_unread => continue,
+            SabiAction::Advance | SabiAction::Skip if !history_open => {
+                if *infotext_zidx == ZIndex(settings.layers.infotext_active) {
+                    UiButtons::InfoText
+                } else if *vncontainer_visibility != Visibility::Hidden {
+                    UiButtons::TextBox
+                } else if nvl.waiting {
+                    UiButtons::Nvl
+                } else {
+                    // No text is waiting for the player, a stage animation is running
+                    complete_animations.write(CompleteAnimationsMessage);
+                    continue;
+                }
+            },
+            SabiAction::Rewind if !history_open => UiButtons::Rewind,
+            SabiAction::History if !history_open => UiButtons::OpenHistory,
+            SabiAction::History | SabiAction::Menu if history_open => UiButtons::ExitHistory,
+            SabiAction::Menu => {
+                info!("[ Open settings ]");
+                commands.spawn(in_game_settings_menu(&preferences, &asset_server, &settings, &active_theme.theme));
+                continue;
+            },
+            _ => continue,
+        };
+        if let Some(entity) = find_button(target) {
+            commands.trigger(Activate { entity });
+        }
+    }
+}
+fn reset_hidden_ui(mut hidden_ui: ResMut<HiddenUi>) {
+    hidden_ui.0 = None;
+}
+fn reset_reading_modes(mut modes: ResMut<ReadingModes>) {
+    *modes = ReadingModes::default();
+}
+/// Records the lines shown to the player, for skipping to stop on the unread ones
+fn track_read_text(
+    mut say_messages: MessageReader<CharacterSayMessage>,
+    mut info_messages: MessageReader<InfoTextMessage>,
+    game_state: Res<VisualNovelState>,
+    mut read_text: ResMut<ReadText>,
+) {
+    if say_messages.read().count() + info_messages.read().count() == 0 {
+        return;
+    }
+    if let Some(index) = game_state.statements.position() {
+        let line = (game_state.act.name.clone(), game_state.scene.name.clone(), index);
+        read_text.current_unread = read_text.lines.insert(line);
+        read_text.dirty |= read_text.current_unread;
+    }
+}
+/// Writes the lines read since the last write to `read_text.json`
+fn flush_read_text(
+    mut read_text: ResMut<ReadText>,
+    settings: Res<SabiSettings>,
+) {
+    if !read_text.dirty {
+        return;
+    }
+    read_text.dirty = false;
+    if let Err(e) = read_text.save(&settings) {
+        warn!("Failed to save read lines: {:#}", e);
+    }
+}
+/// Goes through the lines in skip mode, and on to the next one in auto mode once the text waiting for the player is fully shown
+fn run_reading_modes(
+    mut modes: ResMut<ReadingModes>,
+    read_text: Res<ReadText>,
+    preferences: Res<UserPreferences>,
+    hidden_ui: Res<HiddenUi>,
+    current_sub_state: Res<State<ChatControllerSubState>>,
+    scroll_stopwatch: Res<ChatScrollStopwatch>,
+    message_text: Single<&GUIScrollText, With<MessageText>>,
+    info_text: Single<&GUIScrollText, With<InfoTextComponent>>,
+    q_nvl_lines: Query<&GUIScrollText, With<NvlLine>>,
+    vncontainer_visibility: Single<&Visibility, With<VNContainer>>,
+    infotext_zidx: Single<&ZIndex, With<InfoTextContainer>>,
+    nvl: Res<NvlState>,
+    settings: Res<SabiSettings>,
+    time: Res<Time>,
+    mut action_writer: MessageWriter<SabiActionMessage>,
+) {
+    if !modes.skip && !modes.auto {
+        return;
+    }
+    if hidden_ui.0.is_some() || *current_sub_state != ChatControllerSubState::Default {
+        modes.auto_wait = 0.;
+        return;
+    }
+    if modes.skip {
+        if read_text.current_unread && !preferences.skip_unread {
+            info!("[ Skip mode stopped on unread text ]");
+            modes.skip = false;
+        } else {
+            action_writer.write(SabiActionMessage(SabiAction::Skip));
+        }
+        return;
+    }
+
+    let elapsed = scroll_stopwatch.0.elapsed_secs();
+    let shown = if **infotext_zidx == ZIndex(settings.layers.infotext_active) {
+        info_text.typewriter.is_finished(elapsed)
+    } else if **vncontainer_visibility != Visibility::Hidden {
+        message_text.typewriter.is_finished(elapsed)
+    } else if nvl.waiting {
+        nvl.lines.last()
+            .and_then(|line| q_nvl_lines.get(*line).ok())
+            .is_some_and(|text| text.typewriter.is_finished(elapsed))
+    } else {
+        false
+    };
+    if !shown {
+        modes.auto_wait = 0.;
+        return;
+    }
+    modes.auto_wait += time.delta_secs();
+    if modes.auto_wait >= preferences.auto_delay {
+        modes.auto_wait = 0.;
+        action_writer.write(SabiActionMessage(SabiAction::Advance));
+    }
+}
+/// Highlights the Skip and Auto buttons while their mode is on
+fn highlight_mode_buttons(
+    modes: Res<ReadingModes>,
+    active_theme: Res<ActiveTheme>,
+    mut q_buttons: Query<(&UiButtons, &mut BackgroundColor)>,
+) {
+    let theme = &active_theme.theme.buttons;
+    for (button, mut background) in q_buttons.iter_mut() {
+        let active = match button {
+            UiButtons::Skip => modes.skip,
+            UiButtons::Auto => modes.auto,
+            _ => continue,
+        };
+        background.0 = if active { theme.active.0 } else { theme.background.0 };
+    }
+}
 fn wait_trigger(
     mut msg_reader: MessageReader<ControllersSetStateMessage>,
     mut controller_state: ResMut<NextState<ChatControllerState>>,
@@ -433,8 +1540,22 @@ fn update_gui(
     >,
     concrete_images: Res<Assets<Image>>,
     gui_images: Res<GuiImages>,
+    themes: Res<Assets<UiTheme>>,
+    mut active_theme: ResMut<ActiveTheme>,
+    mut script_gui: ResMut<ScriptGui>,
+    asset_server: Res<AssetServer>,
+    settings: Res<SabiSettings>,
 ) -> Result<(), BevyError> {
     for ev in change_messages.read() {
+        if ev.gui_target == GuiChangeTarget::Theme {
+            info!("[ Change UI theme to '{}' ]", ev.sprite_id);
+            active_theme.request(&ev.sprite_id, &asset_server, &settings);
+            // A theme loaded before does not send a new loading event
+            if let Some(theme) = active_theme.handle.as_ref().and_then(|handle| themes.get(handle)) {
+                active_theme.theme = theme.clone();
+            }
+            continue;
+        }
         let image = gui_images.0.get(&ev.sprite_id)
             .context(format!("GUI asset '{}' does not exist", ev.sprite_id))?;
         match ev.gui_target {
@@ -442,25 +1563,8 @@ fn update_gui(
                 let mut target = q_image_node.iter_mut().find(|q| q.1 == true)
                     .context("Unable to find textbox")?.0;
                 target.image = image.clone();
-                target.image_mode = match ev.image_mode {
-                    GuiImageMode::Sliced => {
-                        let concrete_image = concrete_images.get(image).context("Could not find image")?;
-                        let concrete_image_size = concrete_image.texture_descriptor.size;
-                        let slice_cuts = BorderRect {
-                            top: concrete_image_size.height as f32 / 5.,
-                            bottom: concrete_image_size.height as f32 / 5.,
-                            left: concrete_image_size.width as f32 / 5.,
-                            right: concrete_image_size.width as f32 / 5.
-                        };
-                        NodeImageMode::Sliced(TextureSlicer {
-                            border: slice_cuts,
-                            center_scale_mode: SliceScaleMode::Tile { stretch_value: 1. },
-                            sides_scale_mode: SliceScaleMode::Tile { stretch_value: 1. },
-                            ..default()
-                        })
-                    },
-                    GuiImageMode::Auto => NodeImageMode::Auto
-                };
+                target.image_mode = textbox_image_mode(&ev.image_mode, image, &concrete_images, &active_theme.theme)?;
+                script_gui.textbox = target.clone();
                 commands.insert_resource(CurrentTextBoxBackground(target.clone()));
             }
             GuiChangeTarget::NameBoxBackground => {
@@ -468,9 +1572,115 @@ fn update_gui(
                     .context("Unable to find namebox")?.0;
 
                 target.image = image.clone();
+                script_gui.namebox = target.clone();
             }
+            GuiChangeTarget::Theme => {}
         };
+        script_gui.changed = true;
     }
 
     Ok(())
 }
+/// Image mode of a textbox, slicing the image by the border of the theme or a fifth of its size
+fn textbox_image_mode(
+    mode: &GuiImageMode,
+    image: &Handle<Image>,
+    concrete_images: &Assets<Image>,
+    theme: &UiTheme,
+) -> Result<NodeImageMode, BevyError> {
+    Ok(match mode {
+        GuiImageMode::Sliced => {
+            let concrete_image = concrete_images.get(image).context("Could not find image")?;
+            let concrete_image_size = concrete_image.texture_descriptor.size;
+            let slice_cuts = match theme.textbox.slice_border {
+                Some(border) => BorderRect::all(border),
+                None => BorderRect {
+                    top: concrete_image_size.height as f32 / 5.,
+                    bottom: concrete_image_size.height as f32 / 5.,
+                    left: concrete_image_size.width as f32 / 5.,
+                    right: concrete_image_size.width as f32 / 5.
+                },
+            };
+            NodeImageMode::Sliced(TextureSlicer {
+                border: slice_cuts,
+                center_scale_mode: SliceScaleMode::Tile { stretch_value: 1. },
+                sides_scale_mode: SliceScaleMode::Tile { stretch_value: 1. },
+                ..default()
+            })
+        },
+        GuiImageMode::Auto => NodeImageMode::Auto
+    })
+}
+/// Parses a hex color of a [CharacterConfig], warning when it is not valid
+fn config_color(hex: &Option<String>, character: &str) -> Option<Color> {
+    let hex = hex.as_ref()?;
+    match Srgba::hex(hex) {
+        Ok(color) => Some(color.into()),
+        Err(e) => {
+            warn!("Invalid color '{}' in the config of {}: {}", hex, character, e);
+            None
+        }
+    }
+}
+/// Applies the textbox, namebox, fonts and colors of the speaking character,
+/// falling back to the script GUI and the theme for the ones their config does not set
+fn apply_speaker_style(
+    mut commands: Commands,
+    mut say_messages: MessageReader<CharacterSayMessage>,
+    mut q_image_node: Query<
+        (&mut ImageNode, Has<TextBoxBackground>),
+        Or<(With<TextBoxBackground>, With<NameBoxBackground>)>
+    >,
+    mut name_color: Single<&mut TextColor, (With<NameText>, Without<MessageText>)>,
+    message_style: Single<(&mut TextFont, &mut TextColor), (With<MessageText>, Without<NameText>)>,
+    config_assets: Res<Assets<CharacterConfig>>,
+    speaker_configs: Res<SpeakerConfigs>,
+    concrete_images: Res<Assets<Image>>,
+    gui_images: Res<GuiImages>,
+    active_theme: Res<ActiveTheme>,
+    mut script_gui: ResMut<ScriptGui>,
+    asset_server: Res<AssetServer>,
+    settings: Res<SabiSettings>,
+) -> Result<(), BevyError> {
+    let (mut message_font, mut message_color) = message_style.into_inner();
+    let theme = &active_theme.theme;
+    for ev in say_messages.read() {
+        let config = speaker_configs.0.get(&ev.name).and_then(|handle| config_assets.get(handle));
+
+        // A GUI change right before a new speaker is kept for their lines, as is one made in the middle of them
+        let script_changed = std::mem::take(&mut script_gui.changed);
+        if script_gui.speaker.as_ref() != Some(&ev.name) {
+            script_gui.speaker = Some(ev.name.clone());
+            if !script_changed {
+                for (mut target, is_textbox) in q_image_node.iter_mut() {
+                    let (sprite, script_node) = match is_textbox {
+                        true => (config.and_then(|c| c.textbox.as_ref()), &script_gui.textbox),
+                        false => (config.and_then(|c| c.namebox.as_ref()), &script_gui.namebox),
+                    };
+                    *target = match sprite.and_then(|sprite| gui_images.0.get(sprite)) {
+                        Some(image) => {
+                            let mode = if is_textbox && config.is_some_and(|c| c.textbox_sliced) { GuiImageMode::Sliced } else { GuiImageMode::Auto };
+                            ImageNode {
+                                image: image.clone(),
+                                image_mode: textbox_image_mode(&mode, image, &concrete_images, theme)?,
+                                ..default()
+                            }
+                        },
+                        None => script_node.clone(),
+                    };
+                    if is_textbox {
+                        commands.insert_resource(CurrentTextBoxBackground(target.clone()));
+                    }
+                }
+            }
+        }
+
+        name_color.0 = config.and_then(|c| config_color(&c.name_color, &ev.name)).unwrap_or(theme.namebox.text.color.0);
+        message_color.0 = config.and_then(|c| config_color(&c.text_color, &ev.name)).unwrap_or(theme.textbox.text.color.0);
+        *message_font = theme.textbox.text.font(&asset_server, &settings);
+        if let Some(font) = config.and_then(|c| c.font.as_ref()) {
+            message_font.font = asset_server.load(font);
+        }
+    }
+    Ok(())
+}
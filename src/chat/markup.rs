@@ -0,0 +1,274 @@
+use std::ops::Range;
+
+use bevy::prelude::*;
+
+/// Font size set by a `[size]` tag, either added to the base size (`+4`, `-2`) or absolute (`24`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum FontSize {
+    Relative(f32),
+    Absolute(f32),
+}
+
+impl FontSize {
+    pub fn apply(&self, base: f32) -> f32 {
+        match self {
+            FontSize::Relative(delta) => (base + delta).max(1.),
+            FontSize::Absolute(size) => *size,
+        }
+    }
+}
+
+/// Animations of the glyphs of a run, set by the `[shake]`, `[wave]` and `[rainbow]` tags
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct TextEffects {
+    pub shake: bool,
+    pub wave: bool,
+    pub rainbow: bool,
+}
+
+/// Style of a run of text, set by the tags enclosing it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct SpanStyle {
+    pub bold: bool,
+    pub italic: bool,
+    pub color: Option<Color>,
+    pub size: Option<FontSize>,
+    pub effects: TextEffects,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tag {
+    Bold,
+    Italic,
+    Color(Color),
+    Size(FontSize),
+    Speed(f32),
+    Shake,
+    Wave,
+    Rainbow,
+}
+
+impl Tag {
+    fn name(&self) -> &'static str {
+        match self {
+            Tag::Bold => "b",
+            Tag::Italic => "i",
+            Tag::Color(_) => "color",
+            Tag::Size(_) => "size",
+            Tag::Speed(_) => "speed",
+            Tag::Shake => "shake",
+            Tag::Wave => "wave",
+            Tag::Rainbow => "rainbow",
+        }
+    }
+}
+
+/// Dialogue text with its inline tags parsed:
+/// `[b]`, `[i]`, `[color=#f00]` and `[size=+4]` style the text until their closing tag, e.g. `[/b]`,
+/// `[w=0.5]` waits half a second, even after the last glyph, `[speed=2]` doubles the scrolling speed until `[/speed]`
+/// and `[nw]` goes on without waiting for the player once the text is shown.
+/// `[shake]`, `[wave]` and `[rainbow]` animate the glyphs they enclose.
+/// `[[` writes a literal bracket, and unknown tags are kept as they are.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Markup {
+    /// Text without the tags
+    pub text: String,
+    /// Styled runs covering `text` in order, as byte ranges
+    pub spans: Vec<(Range<usize>, SpanStyle)>,
+    /// Seconds waited before revealing the glyph at each byte offset
+    pub waits: Vec<(usize, f32)>,
+    /// Scrolling speed multiplier from each byte offset onward
+    pub speeds: Vec<(usize, f32)>,
+    pub no_wait: bool,
+}
+
+impl Markup {
+    pub fn parse(source: &str) -> Self {
+        let mut markup = Markup::default();
+        let mut open = Vec::new();
+        let mut rest = source;
+        while let Some(start) = rest.find('[') {
+            markup.push_text(&rest[..start], &open);
+            let after = &rest[start + 1..];
+            if let Some(after) = after.strip_prefix('[') {
+                markup.push_text("[", &open);
+                rest = after;
+                continue;
+            }
+            let Some(end) = after.find(']') else {
+                rest = &rest[start..];
+                break;
+            };
+            if !markup.apply_tag(&after[..end], &mut open) {
+                markup.push_text(&rest[start..start + end + 2], &open);
+            }
+            rest = &after[end + 1..];
+        }
+        markup.push_text(rest, &open);
+        markup
+    }
+
+    fn push_text(&mut self, text: &str, open: &[Tag]) {
+        if text.is_empty() {
+            return;
+        }
+        let style = open.iter().fold(SpanStyle::default(), |mut style, tag| {
+            match tag {
+                Tag::Bold => style.bold = true,
+                Tag::Italic => style.italic = true,
+                Tag::Color(color) => style.color = Some(*color),
+                Tag::Size(size) => style.size = Some(*size),
+                Tag::Speed(_) => {}
+                Tag::Shake => style.effects.shake = true,
+                Tag::Wave => style.effects.wave = true,
+                Tag::Rainbow => style.effects.rainbow = true,
+            }
+            style
+        });
+        let start = self.text.len();
+        self.text.push_str(text);
+        match self.spans.last_mut() {
+            Some((range, last)) if *last == style => range.end = self.text.len(),
+            _ => self.spans.push((start..self.text.len(), style)),
+        }
+    }
+
+    /// Applies a tag, returning false when it is not valid markup
+    fn apply_tag(&mut self, tag: &str, open: &mut Vec<Tag>) -> bool {
+        if let Some(name) = tag.strip_prefix('/') {
+            let Some(index) = open.iter().rposition(|t| t.name() == name) else {
+                return false;
+            };
+            if let Tag::Speed(_) = open.remove(index) {
+                self.speeds.push((self.text.len(), speed_of(open)));
+            }
+            return true;
+        }
+        let (name, value) = match tag.split_once('=') {
+            Some((name, value)) => (name, Some(value.trim())),
+            None => (tag, None),
+        };
+        match (name, value) {
+            ("b", None) => open.push(Tag::Bold),
+            ("i", None) => open.push(Tag::Italic),
+            ("nw", None) => self.no_wait = true,
+            ("shake", None) => open.push(Tag::Shake),
+            ("wave", None) => open.push(Tag::Wave),
+            ("rainbow", None) => open.push(Tag::Rainbow),
+            ("color", Some(value)) => match Srgba::hex(value) {
+                Ok(color) => open.push(Tag::Color(color.into())),
+                Err(_) => return false,
+            },
+            ("size", Some(value)) => {
+                let size = match value.chars().next() {
+                    Some('+' | '-') => value.parse().ok().map(FontSize::Relative),
+                    _ => value.parse().ok().filter(|size| *size > 0.).map(FontSize::Absolute),
+                };
+                match size {
+                    Some(size) => open.push(Tag::Size(size)),
+                    None => return false,
+                }
+            },
+            ("speed", Some(value)) => match value.parse::<f32>() {
+                Ok(speed) if speed > 0. => {
+                    open.push(Tag::Speed(speed));
+                    self.speeds.push((self.text.len(), speed_of(open)));
+                },
+                _ => return false,
+            },
+            ("w", Some(value)) => match value.parse::<f32>() {
+                Ok(wait) if wait >= 0. => self.waits.push((self.text.len(), wait)),
+                _ => return false,
+            },
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// Speed multiplier of the open `[speed]` tags, nested ones multiplying each other
+fn speed_of(open: &[Tag]) -> f32 {
+    open.iter()
+        .map(|tag| if let Tag::Speed(speed) = tag { *speed } else { 1. })
+        .product()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn style(edit: impl FnOnce(&mut SpanStyle)) -> SpanStyle {
+        let mut style = SpanStyle::default();
+        edit(&mut style);
+        style
+    }
+
+    #[test]
+    fn nested_tags() {
+        let markup = Markup::parse("[b]a[i]b[/i][/b]c");
+        assert_eq!(markup.text, "abc");
+        assert_eq!(markup.spans, vec![
+            (0..1, style(|s| s.bold = true)),
+            (1..2, style(|s| { s.bold = true; s.italic = true; })),
+            (2..3, SpanStyle::default()),
+        ]);
+    }
+
+    #[test]
+    fn unclosed_tags() {
+        let markup = Markup::parse("[wave]hi");
+        assert_eq!(markup.text, "hi");
+        assert_eq!(markup.spans, vec![(0..2, style(|s| s.effects.wave = true))]);
+        // A bracket never closed, or a closing tag never opened, is kept as text
+        assert_eq!(Markup::parse("a [b").text, "a [b");
+        assert_eq!(Markup::parse("[/i]a").text, "[/i]a");
+    }
+
+    #[test]
+    fn escaped_bracket() {
+        let markup = Markup::parse("a [[b] c");
+        assert_eq!(markup.text, "a [b] c");
+        assert_eq!(markup.spans, vec![(0..7, SpanStyle::default())]);
+    }
+
+    #[test]
+    fn invalid_values_are_kept_as_text() {
+        assert_eq!(Markup::parse("[color=nope]a[/color]").text, "[color=nope]a[/color]");
+        assert_eq!(Markup::parse("[size=0]a").text, "[size=0]a");
+        assert_eq!(Markup::parse("[size=big]a").text, "[size=big]a");
+        assert_eq!(Markup::parse("[speed=0]a").text, "[speed=0]a");
+        assert_eq!(Markup::parse("[w=-1]a").text, "[w=-1]a");
+    }
+
+    #[test]
+    fn color_and_size() {
+        let markup = Markup::parse("[color=#ff0000][size=+4]a[/size][/color]");
+        assert_eq!(markup.spans, vec![(0..1, style(|s| {
+            s.color = Some(Srgba::hex("#ff0000").unwrap().into());
+            s.size = Some(FontSize::Relative(4.));
+        }))]);
+        assert_eq!(Markup::parse("[size=24]a").spans[0].1.size, Some(FontSize::Absolute(24.)));
+    }
+
+    #[test]
+    fn no_wait() {
+        let markup = Markup::parse("Wait for me[nw]");
+        assert_eq!(markup.text, "Wait for me");
+        assert!(markup.no_wait);
+        assert!(!Markup::parse("Wait for me").no_wait);
+    }
+
+    #[test]
+    fn nested_speeds_multiply() {
+        let markup = Markup::parse("a[speed=2]b[speed=3]c[/speed]d[/speed]e");
+        assert_eq!(markup.text, "abcde");
+        assert_eq!(markup.speeds, vec![(1, 2.), (2, 6.), (3, 2.), (4, 1.)]);
+    }
+
+    #[test]
+    fn waits() {
+        let markup = Markup::parse("a[w=0.5]b[w=1]");
+        assert_eq!(markup.text, "ab");
+        assert_eq!(markup.waits, vec![(1, 0.5), (2, 1.)]);
+    }
+}
@@ -1,11 +1,15 @@
 pub(crate) mod controller;
+pub(crate) mod markup;
+mod scroll_text;
+pub(crate) mod theme;
+mod typewriter;
 mod ui;
 
 pub(crate) use controller::ChatController;
-pub(crate) use controller::GUIScrollText;
+pub(crate) use scroll_text::GUIScrollText;
 pub(crate) use controller::CharacterSayMessage;
 pub(crate) use controller::GUIChangeMessage;
-
-const INFOTEXT_Z_INDEX_ACTIVE: i32 = 4;
-const INFOTEXT_Z_INDEX_INACTIVE: i32 = -1;
-const UI_Z_INDEX: i32 = 5;
\ No newline at end of file
+pub(crate) use ui::basic::{button_node, button_radius};
+pub use controller::QuickMenu;
+pub use controller::QuickMenuButton;
+pub use controller::QuickMenuSelected;
\ No newline at end of file
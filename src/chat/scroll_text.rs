@@ -0,0 +1,222 @@
+use std::ops::Range;
+
+use bevy::prelude::*;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    SabiSettings,
+    chat::{markup::{Markup, SpanStyle, TextEffects}, typewriter::Typewriter}
+};
+
+/// Amplitude of the `[shake]` effect, as a fraction of the font size
+const SHAKE_AMPLITUDE: f32 = 0.06;
+/// Amplitude of the `[wave]` effect, as a fraction of the font size
+const WAVE_AMPLITUDE: f32 = 0.15;
+/// Waves per second of the `[wave]` effect
+const WAVE_SPEED: f32 = 1.2;
+/// Hue cycles per second of the `[rainbow]` effect
+const RAINBOW_SPEED: f32 = 0.4;
+/// Phase offset between two consecutive glyphs of an effect
+const GLYPH_PHASE: f32 = 0.35;
+
+/* Components */
+/// Scrolling text, laid out as one node per word so that lines wrap between words,
+/// each word keeping the whitespace following it so that spaces are as wide as the font makes them.
+/// Words are styled with a pair of [TextSpan]s per run of their [Markup], the hidden half being transparent
+/// until it is revealed, so that the text never moves while it scrolls.
+/// Glyphs with [TextEffects] get a node each, animated by [animate_text_effects].
+#[derive(Component, Default)]
+pub(crate) struct GUIScrollText {
+    pub markup: Markup,
+    pub typewriter: Typewriter,
+    pieces: Vec<TextPiece>,
+}
+/// Glyph node moved or colored by [animate_text_effects]
+#[derive(Component)]
+pub(crate) struct TextEffect {
+    effects: TextEffects,
+    /// Position of the glyph in its text, offsetting the animations of neighbouring glyphs
+    index: usize,
+    font_size: f32,
+    /// Span holding the revealed glyph, colored by `[rainbow]`
+    visible: Entity,
+}
+
+/* Custom types */
+/// Run of text shown through a revealed [TextSpan] followed by a hidden one
+struct TextPiece {
+    range: Range<usize>,
+    visible: Entity,
+    hidden: Entity,
+}
+
+impl GUIScrollText {
+    /// Replaces the text with `source`, respawning the children of `entity` with the fonts, sizes and effects of its tags
+    pub fn set_text(
+        &mut self,
+        commands: &mut Commands,
+        entity: Entity,
+        source: &str,
+        speed: f32,
+        base: (&TextFont, &TextColor),
+        asset_server: &AssetServer,
+        settings: &SabiSettings,
+    ) {
+        self.markup = Markup::parse(source);
+        self.typewriter = Typewriter::new(&self.markup, speed, &settings.text_speeds.punctuation_pauses);
+        self.pieces.clear();
+        commands.entity(entity).despawn_related::<Children>();
+
+        let text = self.markup.text.clone();
+        let mut glyph_index = 0;
+        let mut word_start = 0;
+        while word_start < text.len() {
+            let rest = &text[word_start..];
+            let letters = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            let spaces = rest[letters..].find(|c: char| !c.is_whitespace() || c == '\n').unwrap_or(rest.len() - letters);
+            let mut word_end = word_start + letters + spaces;
+            if word_start < word_end {
+                let word = self.spawn_word(commands, word_start..word_end, &mut glyph_index, base, asset_server, settings);
+                commands.entity(entity).add_child(word);
+            }
+            if text[word_end..].starts_with('\n') {
+                // Full width node pushing the next words to a new line
+                let line_break = commands.spawn(Node { width: percent(100.), ..default() }).id();
+                commands.entity(entity).add_child(line_break);
+                word_end += 1;
+            }
+            word_start = word_end;
+        }
+    }
+
+    /// Empties the text, despawning the word nodes it spawned under `entity`
+    pub fn clear(&mut self, commands: &mut Commands, entity: Entity) {
+        *self = Self::default();
+        commands.entity(entity).despawn_related::<Children>();
+    }
+
+    /// Spawns a word, as a single text node or as a row of glyph nodes when some of its glyphs have effects
+    fn spawn_word(
+        &mut self,
+        commands: &mut Commands,
+        word: Range<usize>,
+        glyph_index: &mut usize,
+        base: (&TextFont, &TextColor),
+        asset_server: &AssetServer,
+        settings: &SabiSettings,
+    ) -> Entity {
+        let runs: Vec<(Range<usize>, SpanStyle)> = self.markup.spans.iter()
+            .filter(|(range, _)| range.start < word.end && range.end > word.start)
+            .map(|(range, style)| (range.start.max(word.start)..range.end.min(word.end), style.clone()))
+            .collect();
+
+        if runs.iter().all(|(_, style)| style.effects == TextEffects::default()) {
+            let node = commands.spawn(Text::default()).id();
+            for (range, style) in runs {
+                *glyph_index += self.markup.text[range.clone()].graphemes(true).count();
+                self.spawn_piece(commands, node, range, &style, base, asset_server, settings);
+            }
+            return node;
+        }
+
+        let row = commands.spawn(Node::default()).id();
+        for (range, style) in runs {
+            let glyph_ranges: Vec<Range<usize>> = self.markup.text[range.clone()].grapheme_indices(true)
+                .map(|(offset, glyph)| range.start + offset..range.start + offset + glyph.len())
+                .collect();
+            for glyph_range in glyph_ranges {
+                let glyph = commands.spawn((Text::default(), TextLayout::new_with_no_wrap(), UiTransform::default())).id();
+                let (visible, font_size) = self.spawn_piece(commands, glyph, glyph_range, &style, base, asset_server, settings);
+                if style.effects != TextEffects::default() {
+                    commands.entity(glyph).insert(TextEffect {
+                        effects: style.effects,
+                        index: *glyph_index,
+                        font_size,
+                        visible,
+                    });
+                }
+                commands.entity(row).add_child(glyph);
+                *glyph_index += 1;
+            }
+        }
+        row
+    }
+
+    /// Spawns the revealed and hidden [TextSpan]s of a run under `parent`, returning the revealed one and its font size
+    fn spawn_piece(
+        &mut self,
+        commands: &mut Commands,
+        parent: Entity,
+        range: Range<usize>,
+        style: &SpanStyle,
+        base: (&TextFont, &TextColor),
+        asset_server: &AssetServer,
+        settings: &SabiSettings,
+    ) -> (Entity, f32) {
+        let (base_font, base_color) = base;
+        let font = match (style.bold, style.italic) {
+            (false, false) => base_font.font.clone(),
+            (true, false) => asset_server.load(&settings.markup_fonts.bold),
+            (false, true) => asset_server.load(&settings.markup_fonts.italic),
+            (true, true) => asset_server.load(&settings.markup_fonts.bold_italic),
+        };
+        let font = TextFont {
+            font,
+            font_size: style.size.map_or(base_font.font_size, |size| size.apply(base_font.font_size)),
+            ..base_font.clone()
+        };
+        let color = style.color.map_or(*base_color, TextColor);
+        let visible = commands.spawn((TextSpan::default(), font.clone(), color)).id();
+        let hidden = commands.spawn((
+            TextSpan(self.markup.text[range.clone()].to_owned()),
+            font.clone(),
+            TextColor(color.0.with_alpha(0.)),
+        )).id();
+        commands.entity(parent).add_children(&[visible, hidden]);
+        self.pieces.push(TextPiece { range, visible, hidden });
+        (visible, font.font_size)
+    }
+
+    /// Moves the part of the text revealed after `elapsed` seconds from the hidden spans to the visible ones
+    pub fn show(&self, elapsed: f32, q_spans: &mut Query<&mut TextSpan>) {
+        let revealed = self.typewriter.revealed(elapsed);
+        for piece in &self.pieces {
+            let split = revealed.clamp(piece.range.start, piece.range.end);
+            // Spans are spawned at the end of the frame their text is set
+            let Ok([mut visible, mut hidden]) = q_spans.get_many_mut([piece.visible, piece.hidden]) else {
+                continue;
+            };
+            if visible.0.len() != split - piece.range.start {
+                visible.0 = self.markup.text[piece.range.start..split].to_owned();
+                hidden.0 = self.markup.text[split..piece.range.end].to_owned();
+            }
+        }
+    }
+}
+
+/// Animates the glyphs of `[shake]`, `[wave]` and `[rainbow]` runs
+pub(crate) fn animate_text_effects(
+    mut q_glyphs: Query<(&TextEffect, &mut UiTransform)>,
+    mut q_colors: Query<&mut TextColor>,
+    time: Res<Time>,
+) {
+    let t = time.elapsed_secs();
+    for (effect, mut transform) in q_glyphs.iter_mut() {
+        let phase = effect.index as f32 * GLYPH_PHASE;
+        let mut offset = Vec2::ZERO;
+        if effect.effects.shake {
+            // Pseudo random jitter, changing about twenty times per second
+            let step = (t * 20.).floor() + effect.index as f32 * 7.;
+            offset += Vec2::new((step * 12.9898).sin(), (step * 78.233).sin()) * effect.font_size * SHAKE_AMPLITUDE;
+        }
+        if effect.effects.wave {
+            offset.y += (t * WAVE_SPEED * std::f32::consts::TAU - phase).sin() * effect.font_size * WAVE_AMPLITUDE;
+        }
+        transform.translation = Val2::px(offset.x, offset.y);
+
+        if effect.effects.rainbow && let Ok(mut color) = q_colors.get_mut(effect.visible) {
+            let hue = ((t * RAINBOW_SPEED + phase / std::f32::consts::TAU) * 360.).rem_euclid(360.);
+            color.0 = Color::hsl(hue, 0.8, 0.65);
+        }
+    }
+}
@@ -0,0 +1,351 @@
+use bevy::{color::palettes::css::BLACK, prelude::*};
+use serde::Deserialize;
+
+use crate::SabiSettings;
+
+/// Length written in themes as `"45px"`, `"20%"`, `"70vw"`, `"10vh"` or `"auto"`
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub(crate) struct ThemeVal(pub Val);
+
+impl TryFrom<String> for ThemeVal {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let value = value.trim();
+        if value == "auto" {
+            return Ok(ThemeVal(Val::Auto));
+        }
+        let units: [(&str, fn(f32) -> Val); 4] = [("px", Val::Px), ("%", Val::Percent), ("vw", Val::Vw), ("vh", Val::Vh)];
+        units.iter()
+            .find_map(|(unit, to_val)| {
+                let number = value.strip_suffix(unit)?;
+                number.trim().parse().ok().map(to_val)
+            })
+            .map(ThemeVal)
+            .ok_or(format!("Invalid length '{}', expected a number followed by px, %, vw or vh", value))
+    }
+}
+
+/// Sides written in themes like CSS: `"10px"`, `"2% 5%"` (vertical, horizontal) or `"1px 2px 3px 4px"` (top, right, bottom, left)
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub(crate) struct ThemeRect(pub UiRect);
+
+impl TryFrom<String> for ThemeRect {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let vals = value.split_whitespace()
+            .map(|v| ThemeVal::try_from(v.to_owned()).map(|v| v.0))
+            .collect::<Result<Vec<_>, _>>()?;
+        let rect = match vals.as_slice() {
+            [all] => UiRect::all(*all),
+            [vertical, horizontal] => UiRect::axes(*horizontal, *vertical),
+            [top, right, bottom, left] => UiRect::new(*left, *right, *top, *bottom),
+            _ => return Err(format!("Invalid sides '{}', expected 1, 2 or 4 lengths", value)),
+        };
+        Ok(ThemeRect(rect))
+    }
+}
+
+/// Color written in themes as a hex string, e.g. `"#fff"` or `"#20202080"`
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub(crate) struct ThemeColor(pub Color);
+
+impl TryFrom<String> for ThemeColor {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Srgba::hex(&value)
+            .map(|color| ThemeColor(color.into()))
+            .map_err(|e| format!("Invalid color '{}': {}", value, e))
+    }
+}
+
+/// Font, size and color of a text
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub(crate) struct TextTheme {
+    /// Font path relative to the assets root, [SabiSettings::font] by default
+    pub font: Option<String>,
+    pub font_size: f32,
+    pub color: ThemeColor,
+}
+
+impl Default for TextTheme {
+    fn default() -> Self {
+        Self {
+            font: None,
+            font_size: 30.,
+            color: ThemeColor(Color::WHITE),
+        }
+    }
+}
+
+impl TextTheme {
+    fn sized(font_size: f32) -> Self {
+        Self { font_size, ..default() }
+    }
+
+    pub fn font(&self, asset_server: &AssetServer, settings: &SabiSettings) -> TextFont {
+        TextFont {
+            font: asset_server.load(self.font.as_ref().unwrap_or(&settings.font)),
+            font_size: self.font_size,
+            ..default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub(crate) struct TextboxTheme {
+    pub width: ThemeVal,
+    pub height: ThemeVal,
+    /// Distance from the bottom of the screen
+    pub margin_bottom: ThemeVal,
+    pub padding: ThemeRect,
+    pub text: TextTheme,
+    /// Space added between two words of the dialogue, on top of the width of the font's spaces
+    pub word_spacing: ThemeVal,
+    /// Border of the nine-slice cuts in image pixels, a fifth of the image by default
+    pub slice_border: Option<f32>,
+}
+
+impl Default for TextboxTheme {
+    fn default() -> Self {
+        Self {
+            width: ThemeVal(Val::Vw(70.)),
+            height: ThemeVal(percent(20.)),
+            margin_bottom: ThemeVal(px(45.)),
+            padding: ThemeRect(UiRect::axes(percent(5.), percent(2.))),
+            text: TextTheme::default(),
+            word_spacing: ThemeVal(px(0.)),
+            slice_border: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub(crate) struct NameboxTheme {
+    pub margin_left: ThemeVal,
+    pub aspect_ratio: f32,
+    /// Space between the left border of the namebox and the name
+    pub text_margin_left: ThemeVal,
+    pub text: TextTheme,
+}
+
+impl Default for NameboxTheme {
+    fn default() -> Self {
+        Self {
+            margin_left: ThemeVal(px(45.)),
+            aspect_ratio: 3.,
+            text_margin_left: ThemeVal(px(35.)),
+            text: TextTheme::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub(crate) struct InfotextTheme {
+    pub max_width: ThemeVal,
+    pub text: TextTheme,
+    /// Space added between two words, on top of the width of the font's spaces
+    pub word_spacing: ThemeVal,
+}
+
+impl Default for InfotextTheme {
+    fn default() -> Self {
+        Self {
+            max_width: ThemeVal(percent(70.)),
+            text: TextTheme::sized(40.),
+            word_spacing: ThemeVal(px(0.)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub(crate) struct ButtonTheme {
+    pub padding: ThemeRect,
+    pub background: ThemeColor,
+    pub border: ThemeColor,
+    pub border_width: ThemeVal,
+    /// Corner radius, fully rounded when not set
+    pub radius: Option<ThemeVal>,
+    pub text: TextTheme,
+    /// Background of the Skip and Auto buttons while their mode is on
+    pub active: ThemeColor,
+}
+
+impl Default for ButtonTheme {
+    fn default() -> Self {
+        Self {
+            padding: ThemeRect(UiRect::axes(px(5.), px(3.))),
+            background: ThemeColor(Color::Srgba(BLACK)),
+            border: ThemeColor(Color::WHITE),
+            border_width: ThemeVal(px(2.)),
+            radius: None,
+            text: TextTheme::sized(20.),
+            active: ThemeColor(Color::srgb(0.35, 0.25, 0.45)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub(crate) struct HistoryTheme {
+    pub width: ThemeVal,
+    pub height: ThemeVal,
+    pub top: ThemeVal,
+    pub padding: ThemeRect,
+    pub title: TextTheme,
+    /// Speaker names, and the act and scene headers
+    pub speaker: TextTheme,
+    pub text: TextTheme,
+}
+
+impl Default for HistoryTheme {
+    fn default() -> Self {
+        Self {
+            width: ThemeVal(percent(70.)),
+            height: ThemeVal(percent(65.)),
+            top: ThemeVal(percent(3.)),
+            padding: ThemeRect(UiRect::new(percent(4.), percent(4.), percent(6.), percent(2.))),
+            title: TextTheme::sized(21.),
+            speaker: TextTheme { color: ThemeColor(Color::srgb(1., 0.85, 0.55)), ..TextTheme::sized(14.) },
+            text: TextTheme::sized(14.),
+        }
+    }
+}
+
+/// Full-screen panel of the NVL mode, whose lines use the fonts and colors of the dialogue and infotext
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub(crate) struct NvlTheme {
+    pub background: ThemeColor,
+    pub padding: ThemeRect,
+    /// Space between two lines
+    pub line_spacing: ThemeVal,
+    pub font_size: f32,
+    /// Space added between two words, on top of the width of the font's spaces
+    pub word_spacing: ThemeVal,
+}
+
+impl Default for NvlTheme {
+    fn default() -> Self {
+        Self {
+            background: ThemeColor(Color::srgba(0., 0., 0., 0.7)),
+            padding: ThemeRect(UiRect::axes(percent(12.), percent(8.))),
+            line_spacing: ThemeVal(px(18.)),
+            font_size: 26.,
+            word_spacing: ThemeVal(px(0.)),
+        }
+    }
+}
+
+/// Box of the text prompts shown by `input` statements
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub(crate) struct PromptTheme {
+    pub width: ThemeVal,
+    pub padding: ThemeRect,
+    pub background: ThemeColor,
+    /// Color of the screen behind the box
+    pub backdrop: ThemeColor,
+    pub text: TextTheme,
+}
+
+impl Default for PromptTheme {
+    fn default() -> Self {
+        Self {
+            width: ThemeVal(percent(40.)),
+            padding: ThemeRect(UiRect::all(px(24.))),
+            background: ThemeColor(Color::srgba(0.08, 0.08, 0.1, 0.95)),
+            backdrop: ThemeColor(Color::srgba(0., 0., 0., 0.5)),
+            text: TextTheme::sized(26.),
+        }
+    }
+}
+
+/// Panel of the settings menu, whose arrows and close button use the style of the buttons
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub(crate) struct SettingsTheme {
+    pub width: ThemeVal,
+    pub padding: ThemeRect,
+    pub background: ThemeColor,
+    /// Color of the screen behind the panel
+    pub backdrop: ThemeColor,
+    /// Space between two options
+    pub row_spacing: ThemeVal,
+    pub title: TextTheme,
+    pub text: TextTheme,
+}
+
+impl Default for SettingsTheme {
+    fn default() -> Self {
+        Self {
+            width: ThemeVal(percent(50.)),
+            padding: ThemeRect(UiRect::all(px(28.))),
+            background: ThemeColor(Color::srgba(0.08, 0.08, 0.1, 0.95)),
+            backdrop: ThemeColor(Color::srgba(0., 0., 0., 0.6)),
+            row_spacing: ThemeVal(px(10.)),
+            title: TextTheme::sized(36.),
+            text: TextTheme::sized(22.),
+        }
+    }
+}
+
+/// Title screen of the [SabiMenusPlugin](crate::SabiMenusPlugin), whose entries use the style of the buttons
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub(crate) struct TitleTheme {
+    pub background: ThemeColor,
+    /// Image covering the screen, relative to the ui path and without extension
+    pub image: Option<String>,
+    pub padding: ThemeRect,
+    pub title: TextTheme,
+    /// Width of the menu entries
+    pub entry_width: ThemeVal,
+    /// Space between two menu entries
+    pub entry_spacing: ThemeVal,
+    pub buttons: ButtonTheme,
+}
+
+impl Default for TitleTheme {
+    fn default() -> Self {
+        Self {
+            background: ThemeColor(Color::srgb(0.06, 0.06, 0.08)),
+            image: None,
+            padding: ThemeRect(UiRect::axes(percent(10.), percent(10.))),
+            title: TextTheme::sized(72.),
+            entry_width: ThemeVal(px(260.)),
+            entry_spacing: ThemeVal(px(14.)),
+            buttons: ButtonTheme {
+                padding: ThemeRect(UiRect::axes(px(10.), px(8.))),
+                text: TextTheme::sized(26.),
+                ..default()
+            },
+        }
+    }
+}
+
+/// Look of the dialogue UI, loaded from `<ui_path>/<name>.theme.json`.
+/// Every field is optional, missing ones keep the look of the default theme.
+#[derive(Asset, TypePath, Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub(crate) struct UiTheme {
+    pub textbox: TextboxTheme,
+    pub namebox: NameboxTheme,
+    pub infotext: InfotextTheme,
+    pub buttons: ButtonTheme,
+    pub history: HistoryTheme,
+    pub nvl: NvlTheme,
+    pub prompt: PromptTheme,
+    pub settings: SettingsTheme,
+    pub title: TitleTheme,
+}
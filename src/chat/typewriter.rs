@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::chat::markup::Markup;
+
+/// Reveal schedule of a scrolling text, one entry per grapheme cluster,
+/// so that multibyte characters and combining marks are never split.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Typewriter {
+    /// Byte offset where each glyph ends, with the second at which it is shown
+    glyphs: Vec<(usize, f32)>,
+    /// Second at which the text is complete, after the `[w]` tags following the last glyph
+    end: f32,
+}
+
+impl Typewriter {
+    /// Schedules the text of `markup` at `speed` glyphs per second, following its `[w]` and `[speed]` tags
+    /// and waiting the extra seconds of `pauses` after each punctuation run
+    pub fn new(markup: &Markup, speed: f32, pauses: &HashMap<char, f32>) -> Self {
+        let pause_of = |glyph: &str| {
+            let mut chars = glyph.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => pauses.get(&c).copied(),
+                _ => None,
+            }
+        };
+
+        let mut glyphs = Vec::new();
+        let mut time = 0.;
+        let mut multiplier = 1.;
+        let mut waits = markup.waits.iter().peekable();
+        let mut speeds = markup.speeds.iter().peekable();
+        let mut indices = markup.text.grapheme_indices(true).peekable();
+        while let Some((start, glyph)) = indices.next() {
+            while let Some((_, wait)) = waits.next_if(|(offset, _)| *offset <= start) {
+                time += wait;
+            }
+            while let Some((_, speed)) = speeds.next_if(|(offset, _)| *offset <= start) {
+                multiplier = *speed;
+            }
+            if speed > 0. {
+                time += 1. / (speed * multiplier);
+            }
+            glyphs.push((start + glyph.len(), time));
+            // A run of punctuation, e.g. "?!" or "...", pauses once after its last glyph
+            if let Some(pause) = pause_of(glyph) {
+                let run_continues = indices.peek().is_some_and(|(_, next)| pause_of(next).is_some());
+                if !run_continues {
+                    time += pause;
+                }
+            }
+        }
+        // Waits after the last glyph hold the end of the text, e.g. before `[nw]` goes on
+        let trailing: f32 = waits.map(|(_, wait)| wait).sum();
+        let end = glyphs.last().map_or(0., |(_, time)| *time) + trailing;
+        Self { glyphs, end }
+    }
+
+    /// Length in bytes of the text shown after `elapsed` seconds
+    pub fn revealed(&self, elapsed: f32) -> usize {
+        let shown = self.glyphs.partition_point(|(_, time)| *time <= elapsed);
+        shown.checked_sub(1).map_or(0, |last| self.glyphs[last].0)
+    }
+
+    /// Whether every glyph is shown after `elapsed` seconds
+    pub fn is_finished(&self, elapsed: f32) -> bool {
+        self.end <= elapsed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trailing_wait_delays_the_end() {
+        let typewriter = Typewriter::new(&Markup::parse("ab[w=1][nw]"), 1., &HashMap::new());
+        assert_eq!(typewriter.revealed(2.), 2);
+        assert!(!typewriter.is_finished(2.5));
+        assert!(typewriter.is_finished(3.));
+    }
+
+    #[test]
+    fn graphemes_are_revealed_whole() {
+        // "e" with a combining acute accent, then a family emoji joined by zero width joiners
+        let text = "e\u{301}\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}a";
+        let typewriter = Typewriter::new(&Markup::parse(text), 1., &HashMap::new());
+        assert_eq!(typewriter.revealed(1.), 3);
+        assert_eq!(typewriter.revealed(2.), 21);
+        assert_eq!(typewriter.revealed(3.), text.len());
+    }
+
+    #[test]
+    fn speed_changes_mid_line() {
+        let typewriter = Typewriter::new(&Markup::parse("ab[speed=2]cd[/speed]e"), 1., &HashMap::new());
+        assert_eq!(typewriter.revealed(2.), 2);
+        assert_eq!(typewriter.revealed(2.5), 3);
+        assert_eq!(typewriter.revealed(3.), 4);
+        assert_eq!(typewriter.revealed(3.9), 4);
+        assert_eq!(typewriter.revealed(4.), 5);
+    }
+
+    #[test]
+    fn revealed_at_boundaries() {
+        let typewriter = Typewriter::new(&Markup::parse("abc"), 2., &HashMap::new());
+        assert_eq!(typewriter.revealed(0.), 0);
+        assert_eq!(typewriter.revealed(0.49), 0);
+        // A glyph is shown from the exact second it is due
+        assert_eq!(typewriter.revealed(0.5), 1);
+        assert_eq!(typewriter.revealed(1.5), 3);
+        assert_eq!(typewriter.revealed(100.), 3);
+        assert!(!typewriter.is_finished(1.49));
+        assert!(typewriter.is_finished(1.5));
+
+        let empty = Typewriter::new(&Markup::parse(""), 2., &HashMap::new());
+        assert_eq!(empty.revealed(0.), 0);
+        assert!(empty.is_finished(0.));
+    }
+}
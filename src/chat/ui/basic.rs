@@ -1,27 +1,133 @@
-use bevy::{color::palettes::css::BLACK, prelude::*};
+use bevy::prelude::*;
 use bevy_ui_widgets::Button;
 
 use crate::{
-        chat::{
-            GUIScrollText, INFOTEXT_Z_INDEX_INACTIVE, UI_Z_INDEX, controller::{
-                InfoTextComponent, InfoTextContainer, MessageText, NameBoxBackground, NameText, TextBoxBackground, UiButtons, VNContainer, VnCommands
-            }, ui::FONT_PATH
+        SabiSettings, chat::{
+            GUIScrollText, controller::{
+                InfoTextComponent, InfoTextContainer, MessageText, NameBoxBackground, NameText, NvlLines, NvlPanel, QuickMenu, TextBoxBackground, UiButtons,
+                VNContainer, VnCommands
+            },
+            theme::{ButtonTheme, UiTheme}
     },
     compiler::controller::SabiState
 };
 
-pub(in crate::chat) fn backplate_container() -> impl Bundle {
+/* Nodes */
+// Layouts depending on the theme, also applied again when the theme changes
+
+pub(in crate::chat) fn backplate_container_node(theme: &UiTheme) -> Node {
+    Node {
+        width: theme.textbox.width.0,
+        height: theme.textbox.height.0,
+        margin: UiRect::all(Val::Auto).with_bottom(theme.textbox.margin_bottom.0),
+        display: Display::Flex,
+        flex_direction: FlexDirection::Column,
+        ..default()
+    }
+}
+
+pub(in crate::chat) fn namebox_node(theme: &UiTheme) -> Node {
+    Node {
+        margin: UiRect::default().with_left(theme.namebox.margin_left.0),
+        aspect_ratio: Some(theme.namebox.aspect_ratio),
+        align_items: AlignItems::Center,
+        ..default()
+    }
+}
+
+pub(in crate::chat) fn nametext_node(theme: &UiTheme) -> Node {
+    Node {
+        margin: UiRect::default().with_left(theme.namebox.text_margin_left.0),
+        ..default()
+    }
+}
+
+pub(in crate::chat) fn textbox_node(theme: &UiTheme) -> Node {
+    Node {
+        width: percent(100.),
+        min_height: percent(100.),
+        padding: theme.textbox.padding.0,
+        ..default()
+    }
+}
+
+pub(in crate::chat) fn messagetext_node(theme: &UiTheme) -> Node {
+    Node {
+        flex_grow: 1.,
+        flex_wrap: FlexWrap::Wrap,
+        align_content: AlignContent::FlexStart,
+        column_gap: theme.textbox.word_spacing.0,
+        ..default()
+    }
+}
+
+pub(in crate::chat) fn infotext_node(theme: &UiTheme) -> Node {
+    Node {
+        align_items: AlignItems::Center,
+        justify_content: JustifyContent::Center,
+        flex_wrap: FlexWrap::Wrap,
+        column_gap: theme.infotext.word_spacing.0,
+        max_width: theme.infotext.max_width.0,
+        ..default()
+    }
+}
+
+pub(in crate::chat) fn nvl_panel_node(theme: &UiTheme) -> Node {
+    Node {
+        width: percent(100),
+        height: percent(100),
+        position_type: PositionType::Absolute,
+        top: px(0),
+        left: px(0),
+        padding: theme.nvl.padding.0,
+        flex_direction: FlexDirection::Column,
+        ..default()
+    }
+}
+
+pub(in crate::chat) fn nvl_lines_node(theme: &UiTheme) -> Node {
+    Node {
+        flex_grow: 1.,
+        flex_direction: FlexDirection::Column,
+        row_gap: theme.nvl.line_spacing.0,
+        overflow: Overflow::clip(),
+        ..default()
+    }
+}
+
+pub(in crate::chat) fn nvl_line_node(theme: &UiTheme) -> Node {
+    Node {
+        width: percent(100),
+        flex_wrap: FlexWrap::Wrap,
+        column_gap: theme.nvl.word_spacing.0,
+        ..default()
+    }
+}
+
+pub(crate) fn button_node(theme: &ButtonTheme, position_type: PositionType) -> Node {
+    Node {
+        position_type,
+        right: percent(2.),
+        top: percent(2.),
+        border: UiRect::all(theme.border_width.0),
+        align_items: AlignItems::Center,
+        justify_content: JustifyContent::Center,
+        padding: theme.padding.0,
+        ..default()
+    }
+}
+
+pub(crate) fn button_radius(theme: &ButtonTheme) -> BorderRadius {
+    theme.radius.map_or(BorderRadius::MAX, |radius| BorderRadius::all(radius.0))
+}
+
+/* Bundles */
+
+pub(in crate::chat) fn backplate_container(settings: &SabiSettings, theme: &UiTheme) -> impl Bundle {
     (
-        Node {
-            width: Val::Vw(70.),
-            height: percent(20.),
-            margin: UiRect::all(Val::Auto).with_bottom(px(45.)),
-            display: Display::Flex,
-            flex_direction: FlexDirection::Column,
-            ..default()
-        },
+        backplate_container_node(theme),
         Visibility::Hidden,
-        ZIndex(UI_Z_INDEX),
+        ZIndex(settings.layers.ui),
         VNContainer,
         DespawnOnEnter(SabiState::Idle)
     )
@@ -33,50 +139,30 @@ pub(in crate::chat) fn top_section() -> impl Bundle {
     Node::default()
 }
 
-pub(in crate::chat) fn namebox() -> impl Bundle {
+pub(in crate::chat) fn namebox(theme: &UiTheme) -> impl Bundle {
     (
         ImageNode::default(),
-        Node {
-            margin: UiRect::default().with_left(px(45.)),
-            aspect_ratio: Some(3.),
-            align_items: AlignItems::Center,
-            ..default()
-        },
+        namebox_node(theme),
         Visibility::Inherited,
         NameBoxBackground,
     )
 }
 
-pub(in crate::chat) fn nametext(asset_server: &Res<AssetServer>) -> impl Bundle {
+pub(in crate::chat) fn nametext(asset_server: &Res<AssetServer>, settings: &SabiSettings, theme: &UiTheme) -> impl Bundle {
     (
-        Node {
-            margin: UiRect::default().with_left(px(35.)),
-            ..default()
-        },
+        nametext_node(theme),
         Text::new("TEST"),
-        TextFont {
-            font: asset_server.load(FONT_PATH),
-            font_size: 30.0,
-            ..default()
-        },
+        theme.namebox.text.font(asset_server, settings),
+        TextColor(theme.namebox.text.color.0),
         NameText
     )
 }
 
-pub(in crate::chat) fn textbox() -> impl Bundle {
+pub(in crate::chat) fn textbox(settings: &SabiSettings, theme: &UiTheme) -> impl Bundle {
     (
         ImageNode::default(),
-        Node {
-            width: percent(100.),
-            min_height: percent(100.),
-            padding: UiRect {
-                top: percent(2.),
-                bottom: percent(2.),
-                ..UiRect::horizontal(percent(5.))
-            },
-            ..default()
-        },
-        ZIndex(UI_Z_INDEX),
+        textbox_node(theme),
+        ZIndex(settings.layers.ui),
         Visibility::Inherited,
         UiButtons::TextBox,
         Button,
@@ -84,21 +170,17 @@ pub(in crate::chat) fn textbox() -> impl Bundle {
     )
 }
 
-pub(in crate::chat) fn messagetext(asset_server: &Res<AssetServer>) -> impl Bundle {
+pub(in crate::chat) fn messagetext(asset_server: &Res<AssetServer>, settings: &SabiSettings, theme: &UiTheme) -> impl Bundle {
     (
-        Text::new("TEST"),
         GUIScrollText::default(),
-        Node::default(),
-        TextFont {
-            font: asset_server.load(FONT_PATH),
-            font_size: 30.0,
-            ..default()
-        },
+        messagetext_node(theme),
+        TextColor(theme.textbox.text.color.0),
+        theme.textbox.text.font(asset_server, settings),
         MessageText
     )
 }
 
-pub(in crate::chat) fn infotext_container(asset_server: &Res<AssetServer>) -> impl Bundle {
+pub(in crate::chat) fn infotext_container(asset_server: &Res<AssetServer>, settings: &SabiSettings, theme: &UiTheme) -> impl Bundle {
     (
         Node {
             width: percent(100),
@@ -112,74 +194,101 @@ pub(in crate::chat) fn infotext_container(asset_server: &Res<AssetServer>) -> im
             left: px(0),
             ..default()
         },
-        ZIndex(INFOTEXT_Z_INDEX_INACTIVE),
+        ZIndex(settings.layers.infotext_inactive),
         Button,
         UiButtons::InfoText,
         InfoTextContainer,
         DespawnOnExit(SabiState::Running),
         children![
-            infotext(asset_server)
+            infotext(asset_server, settings, theme)
         ]
     )
 }
 
-fn infotext(asset_server: &Res<AssetServer>) -> impl Bundle {
+pub(in crate::chat) fn nvl_panel(
+    asset_server: &AssetServer,
+    settings: &SabiSettings,
+    theme: &UiTheme,
+    quick_menu: &QuickMenu,
+) -> Result<impl Bundle, BevyError> {
+    Ok((
+        nvl_panel_node(theme),
+        BackgroundColor(theme.nvl.background.0),
+        ZIndex(settings.layers.ui),
+        Visibility::Hidden,
+        Button,
+        UiButtons::Nvl,
+        NvlPanel,
+        DespawnOnExit(SabiState::Running),
+        children![
+            (nvl_lines_node(theme), NvlLines),
+            vn_commands(asset_server, settings, theme, quick_menu)?,
+        ]
+    ))
+}
+
+fn infotext(asset_server: &Res<AssetServer>, settings: &SabiSettings, theme: &UiTheme) -> impl Bundle {
     (
-        Text::new(""),
         GUIScrollText::default(),
-        Node {
-            align_items: AlignItems::Center,
-            justify_content: JustifyContent::Center,
-            max_width: percent(70.),
-            ..default()
-        },
-        TextFont {
-            font: asset_server.load(FONT_PATH),
-            font_size: 40.0,
-            ..default()
-        },
-        TextLayout {
-            justify: Justify::Center,
-            linebreak: LineBreak::WordBoundary,
-        },
+        infotext_node(theme),
+        TextColor(theme.infotext.text.color.0),
+        theme.infotext.text.font(asset_server, settings),
         Visibility::Hidden,
-        ZIndex(UI_Z_INDEX),
+        ZIndex(settings.layers.ui),
         InfoTextComponent,
     )
 }
 
-pub(in crate::chat::ui) fn button(action: UiButtons) -> Result<impl Bundle, BevyError> {
+pub(in crate::chat) fn button(
+    action: UiButtons,
+    asset_server: &AssetServer,
+    settings: &SabiSettings,
+    theme: &ButtonTheme,
+) -> Result<impl Bundle, BevyError> {
     let (button_text, position_type) = match action {
         UiButtons::OpenHistory => (String::from("History"), PositionType::Relative),
         UiButtons::ExitHistory => (String::from("Close"), PositionType::Absolute),
         UiButtons::Rewind      => (String::from("Rewind"), PositionType::Relative),
+        UiButtons::ConfirmInput => (String::from("OK"), PositionType::Relative),
+        UiButtons::OpenSettings => (String::from("Settings"), PositionType::Relative),
+        UiButtons::HideUi      => (String::from("Hide"), PositionType::Relative),
+        UiButtons::ExportLog   => (String::from("Export"), PositionType::Relative),
+        UiButtons::QuickSave   => (String::from("Q.Save"), PositionType::Relative),
+        UiButtons::QuickLoad   => (String::from("Q.Load"), PositionType::Relative),
+        UiButtons::Save        => (String::from("Save"), PositionType::Relative),
+        UiButtons::Load        => (String::from("Load"), PositionType::Relative),
+        UiButtons::Skip        => (String::from("Skip"), PositionType::Relative),
+        UiButtons::Auto        => (String::from("Auto"), PositionType::Relative),
+        UiButtons::Custom(ref label) => (label.clone(), PositionType::Relative),
         other                  => return Err(anyhow::anyhow!("{:?} is not a valid button!", other).into()),
     };
     
     Ok((
-        Node {
-            position_type,
-            right: percent(2.),
-            top: percent(2.),
-            border: UiRect::all(px(2)),
-            align_items: AlignItems::Center,
-            justify_content: JustifyContent::Center,
-            padding: UiRect { left: px(5), right: px(5), top: px(3), bottom: px(3) },
-            ..default()
-        },
-        BorderColor::all(Color::WHITE),
-        BorderRadius::MAX,
-        BackgroundColor(Color::Srgba(BLACK)),
+        button_node(theme, position_type),
+        BorderColor::all(theme.border.0),
+        button_radius(theme),
+        BackgroundColor(theme.background.0),
         action,
         Button,
-        children![
+        children![(
             Text::new(button_text),
+            theme.text.font(asset_server, settings),
+            TextColor(theme.text.color.0),
             TextShadow::default()
-        ]
+        )]
     ))
 }
 
-pub(in crate::chat) fn vn_commands() -> Result<impl Bundle, BevyError> {
+/// Quick menu row, with the buttons of the [QuickMenu] in order
+pub(in crate::chat) fn vn_commands(
+    asset_server: &AssetServer,
+    settings: &SabiSettings,
+    theme: &UiTheme,
+    quick_menu: &QuickMenu,
+) -> Result<impl Bundle, BevyError> {
+    let buttons = quick_menu.buttons.iter()
+        .map(|b| button(b.into(), asset_server, settings, &theme.buttons))
+        .collect::<Result<Vec<_>, _>>()?;
     Ok((
         Node {
             position_type: PositionType::Absolute,
@@ -192,10 +301,7 @@ pub(in crate::chat) fn vn_commands() -> Result<impl Bundle, BevyError> {
             ..default()
         },
         VnCommands,
-        ZIndex(UI_Z_INDEX),
-        children![
-            button(UiButtons::Rewind)?,
-            button(UiButtons::OpenHistory)?,
-        ]
+        ZIndex(settings.layers.ui),
+        Children::spawn(SpawnIter(buttons.into_iter()))
     ))
 }
\ No newline at end of file
@@ -3,19 +3,24 @@ use bevy::{asset::AssetServer, ecs::relationship::RelatedSpawner};
 use bevy::prelude::*;
 use bevy_ui_widgets::{CoreScrollbarThumb, Scrollbar};
 
-use crate::chat::controller::{HistoryScrollbar, HistoryText, UiButtons};
-use crate::chat::ui::FONT_PATH;
+use crate::chat::controller::{HistoryEntry, HistoryEntryText, HistoryScrollbar, HistorySearch, HistorySpeaker, HistoryText, UiButtons};
+use crate::chat::theme::UiTheme;
 use crate::chat::ui::basic::button;
-use crate::{VisualNovelState, chat::{UI_Z_INDEX, controller::{CurrentTextBoxBackground, HistoryPanel}}};
+use crate::{EnteredText, HistoryItem, HistoryLine, SabiSettings, VisualNovelState, chat::controller::{CurrentTextBoxBackground, HistoryPanel}};
 
 pub(crate) fn history_panel(
     current_plate: Res<CurrentTextBoxBackground>,
     game_state: &ResMut<VisualNovelState>,
     asset_server: &Res<AssetServer>,
+    settings: &SabiSettings,
+    theme: &UiTheme,
 ) -> Result<impl Bundle, BevyError> {
     
-    let history_text = history_text(asset_server, game_state)?;
-    let exit_history_button = button(UiButtons::ExitHistory)?;
+    let history_text = history_text(asset_server, game_state, settings, theme);
+    let history_title = history_title(asset_server, settings, theme);
+    let history_search = history_search(asset_server, settings, theme);
+    let exit_history_button = button(UiButtons::ExitHistory, asset_server, settings, &theme.buttons)?;
+    let export_button = button(UiButtons::ExportLog, asset_server, settings, &theme.buttons)?;
     
     Ok((
         ImageNode {
@@ -25,34 +30,40 @@ pub(crate) fn history_panel(
         },
         Node {
             position_type: PositionType::Absolute,
-            width: percent(70.),
-            height: percent(65.),
-            top: percent(3.),
+            width: theme.history.width.0,
+            height: theme.history.height.0,
+            top: theme.history.top.0,
             display: Display::Flex,
             justify_content: JustifyContent::Center,
-            padding: UiRect {
-                top: percent(6.),
-                bottom: percent(2.),
-                ..UiRect::horizontal(percent(4.))
-            },
+            padding: theme.history.padding.0,
             ..default()
         },
-        ZIndex(UI_Z_INDEX),
+        ZIndex(settings.layers.ui),
         HistoryPanel,
         Children::spawn(
             SpawnWith(|parent: &mut RelatedSpawner<ChildOf>| {
-                parent.spawn(history_title());
+                parent.spawn(history_title);
+                parent.spawn(history_search);
                 let scroll_area_id = parent.spawn((
                     history_text,
                 )).id();
                 parent.spawn(scrollbar(scroll_area_id));
                 parent.spawn(exit_history_button);
+                parent.spawn((
+                    Node {
+                        position_type: PositionType::Absolute,
+                        bottom: percent(3.),
+                        right: percent(2.),
+                        ..default()
+                    },
+                    children![export_button],
+                ));
             })
         ),
     ))
 }
 
-fn history_title() -> impl Bundle {
+fn history_title(asset_server: &AssetServer, settings: &SabiSettings, theme: &UiTheme) -> impl Bundle {
     (
         Node {
             position_type: PositionType::Absolute,
@@ -60,10 +71,70 @@ fn history_title() -> impl Bundle {
             ..default()
         },
         Text::new("History"),
-        TextFont {
-            font_size: 21.,
+        theme.history.title.font(asset_server, settings),
+        TextColor(theme.history.title.color.0),
+    )
+}
+
+fn history_search(asset_server: &AssetServer, settings: &SabiSettings, theme: &UiTheme) -> impl Bundle {
+    (
+        Node {
+            position_type: PositionType::Absolute,
+            top: percent(2.),
+            left: percent(4.),
+            min_width: percent(25.),
+            padding: UiRect::axes(px(8.), px(3.)),
+            border: UiRect::all(px(1.)),
             ..default()
-        }
+        },
+        BorderColor::all(theme.history.text.color.0.with_alpha(0.5)),
+        BorderRadius::all(px(4.)),
+        children![(
+            Text::new("Search..."),
+            theme.history.text.font(asset_server, settings),
+            TextColor(theme.history.text.color.0.with_alpha(0.7)),
+            HistorySearch::default(),
+        )]
+    )
+}
+
+/// Color of the history rows which cannot be gone back to
+pub(crate) fn dimmed(color: Color) -> Color {
+    color.with_alpha(color.alpha() * 0.5)
+}
+
+/// Row of the history, dimmed when [VisualNovelState::can_rewind_to] does not allow going back to its line
+fn history_entry(
+    speaker: &str,
+    text: &str,
+    rewindable: bool,
+    asset_server: &AssetServer,
+    settings: &SabiSettings,
+    theme: &UiTheme,
+) -> impl Bundle {
+    (
+        Node {
+            width: percent(100.),
+            column_gap: px(10.),
+            padding: UiRect::vertical(px(3.)),
+            flex_shrink: 0.,
+            ..default()
+        },
+        HistoryEntry { search: format!("{} {}", speaker, text).to_lowercase(), rewindable },
+        children![
+            (
+                Text::new(speaker),
+                theme.history.speaker.font(asset_server, settings),
+                TextColor(if rewindable { theme.history.speaker.color.0 } else { dimmed(theme.history.speaker.color.0) }),
+                HistorySpeaker,
+            ),
+            (
+                Text::new(text),
+                theme.history.text.font(asset_server, settings),
+                TextColor(if rewindable { theme.history.text.color.0 } else { dimmed(theme.history.text.color.0) }),
+                HistoryEntryText,
+            )
+        ]
     )
 }
 
@@ -93,10 +164,22 @@ fn scrollbar(entity: Entity) -> impl Bundle {
     )
 }
 
-fn history_text(asset_server: &Res<AssetServer>, game_state: &ResMut<VisualNovelState>) -> Result<impl Bundle, BevyError> {
-    let history_text = game_state.history_summary()?.join("\n");
-    let font_handle = asset_server.load(FONT_PATH);
-    Ok((
+fn history_text(asset_server: &Res<AssetServer>, game_state: &ResMut<VisualNovelState>, settings: &SabiSettings, theme: &UiTheme) -> impl Bundle {
+    let entries = game_state.history.iter()
+        .enumerate()
+        .filter_map(|(index, item)| {
+            let (speaker, text) = match item {
+                HistoryItem::Statement { line: Some(HistoryLine { speaker, text }), .. } => (speaker.as_deref().unwrap_or_default(), text.as_str()),
+                HistoryItem::Statement { input: Some(EnteredText { value, .. }), .. } => ("Input", value.as_str()),
+                HistoryItem::Statement { line: None, .. } => return None,
+                HistoryItem::Act(name) => ("Act", name.as_str()),
+                HistoryItem::Scene(name) => ("Scene", name.as_str()),
+            };
+            let rewindable = game_state.can_rewind_to(index);
+            Some((history_entry(speaker, text, rewindable, asset_server, settings, theme), rewindable.then_some(index)))
+        })
+        .collect::<Vec<_>>();
+    (
         Node {
             display: Display::Flex,
             flex_direction: FlexDirection::Column,
@@ -106,18 +189,17 @@ fn history_text(asset_server: &Res<AssetServer>, game_state: &ResMut<VisualNovel
             flex_shrink: 0.,
             ..default()
         },
-        children![
-            (
-                Text(history_text),
-                TextFont {
-                    font: font_handle,
-                    font_size: 14.,
-                    ..default()
-                },
-            )
-        ],
-        ZIndex(UI_Z_INDEX),
+        // Only the rows which can be gone back to are clickable
+        Children::spawn(SpawnWith(move |parent: &mut RelatedSpawner<ChildOf>| {
+            for (entry, rewind_index) in entries {
+                let mut row = parent.spawn(entry);
+                if let Some(index) = rewind_index {
+                    row.insert((Button, UiButtons::HistoryEntry(index)));
+                }
+            }
+        })),
+        ZIndex(settings.layers.ui),
         ScrollPosition(Vec2::new(0., 0.)),
         HistoryText
-    ))
+    )
 }
\ No newline at end of file
@@ -1,4 +1,3 @@
 pub(in crate::chat) mod basic;
 pub(in crate::chat) mod history;
-
-const FONT_PATH: &str = "sabi/fonts/ALLER.ttf";
\ No newline at end of file
+pub(in crate::chat) mod prompt;
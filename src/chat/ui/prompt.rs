@@ -0,0 +1,62 @@
+use bevy::prelude::*;
+
+use crate::{
+    SabiSettings,
+    chat::{controller::{TextPromptField, TextPromptPanel, UiButtons}, theme::UiTheme, ui::basic::button},
+    compiler::controller::SabiState
+};
+
+pub(in crate::chat) fn text_prompt(
+    prompt: &str,
+    asset_server: &AssetServer,
+    settings: &SabiSettings,
+    theme: &UiTheme,
+) -> Result<impl Bundle, BevyError> {
+    let confirm_button = button(UiButtons::ConfirmInput, asset_server, settings, &theme.buttons)?;
+    let text_font = theme.prompt.text.font(asset_server, settings);
+    let text_color = TextColor(theme.prompt.text.color.0);
+
+    Ok((
+        // Backdrop catching the clicks meant for the dialogue UI
+        Node {
+            width: percent(100),
+            height: percent(100),
+            position_type: PositionType::Absolute,
+            top: px(0),
+            left: px(0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        BackgroundColor(theme.prompt.backdrop.0),
+        ZIndex(settings.layers.ui),
+        TextPromptPanel,
+        DespawnOnExit(SabiState::Running),
+        children![(
+            Node {
+                width: theme.prompt.width.0,
+                padding: theme.prompt.padding.0,
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: px(16),
+                ..default()
+            },
+            BackgroundColor(theme.prompt.background.0),
+            BorderRadius::all(px(8)),
+            children![
+                (Text::new(prompt), text_font.clone(), text_color),
+                (
+                    Node {
+                        width: percent(100),
+                        padding: UiRect::axes(px(10), px(6)),
+                        border: UiRect::all(theme.buttons.border_width.0),
+                        ..default()
+                    },
+                    BorderColor::all(theme.buttons.border.0),
+                    children![(Text::new("|"), text_font, text_color, TextPromptField)]
+                ),
+                confirm_button,
+            ]
+        )]
+    ))
+}
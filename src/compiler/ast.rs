@@ -8,7 +8,8 @@ use std::collections::HashMap;
 use crate::{
     background::controller::{BackgroundDirection, BackgroundOperation},
     character::{CharacterOperation, controller::{CharacterDirection, CharacterPosition, SpawnInfo}},
-    chat::controller::{GuiChangeTarget, GuiImageMode}
+    chat::controller::{GuiChangeTarget, GuiImageMode, InputTarget, NvlOperation},
+    tween::{Timing, easing_from_name}
 };
 
 #[derive(Parser)]
@@ -30,7 +31,7 @@ pub(crate) trait Evaluate {
     fn evaluate(&self) -> Result<Expr>;
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub(crate) enum Expr {
     Number(f64),
     String(String),
@@ -95,38 +96,43 @@ pub(crate) struct Act {
     pub entrypoint: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub(crate) enum CodeStatement {
-    Log { exprs: Vec<Expr> }
+    Log { exprs: Vec<Expr> },
+    /// Shows a text field with the `prompt`, storing the confirmed text in `target`
+    Input { target: InputTarget, prompt: Box<Expr>, max_length: Option<usize> },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub(crate) enum StageCommand {
-    BackgroundChange { operation: BackgroundOperation },
+    BackgroundChange { operation: BackgroundOperation, timing: Timing },
     GUIChange { gui_target: GuiChangeTarget, sprite_expr: Box<Expr>, image_mode: GuiImageMode },
+    NvlChange { operation: NvlOperation },
     SceneChange { scene_expr: Box<Expr> },
     ActChange { act_expr: Box<Expr> },
-    CharacterChange { character: String, operation: CharacterOperation },
+    CharacterChange { character: String, operation: CharacterOperation, timing: Timing },
+    /// Commands joined with `with`, invoked at the same time
+    Group(Vec<StageCommand>),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub(crate) enum TextItem {
     Dialogue(Dialogue),
     InfoText(InfoText),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub(crate) struct InfoText {
     pub infotext: Expr
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub(crate) struct Dialogue {
     pub character: String,
     pub dialogue: Expr
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub(crate) enum Statement {
     Code(CodeStatement),
     Stage(StageCommand),
@@ -211,10 +217,10 @@ fn build_character_spawn_directive(character: &str, action: &str, mut inner_acti
                     _ => {added_action = None;}
                 };
             }
-            StageCommand::CharacterChange { character: character.to_string(), operation: CharacterOperation::Spawn(info) }
+            StageCommand::CharacterChange { character: character.to_string(), operation: CharacterOperation::Spawn(info), timing: Timing::default() }
         },
         "disappears" | "fade out" => {
-            StageCommand::CharacterChange { character: character.to_string(), operation: CharacterOperation::Despawn(action == "fade out") }
+            StageCommand::CharacterChange { character: character.to_string(), operation: CharacterOperation::Despawn(action == "fade out"), timing: Timing::default() }
         },
         other => bail!("Unexpected action in Character Spawn Directive command: {:?}", other)
     };
@@ -239,7 +245,7 @@ fn build_character_direction_directive(character: &str, action: &str, mut inner_
                 },
                 _ => { bail!("Character direction directive needs direction argument [\"left\", \"right\"]"); }
             };
-            StageCommand::CharacterChange { character: character.to_string(), operation: CharacterOperation::Look(direction) }
+            StageCommand::CharacterChange { character: character.to_string(), operation: CharacterOperation::Look(direction), timing: Timing::default() }
         }
         other => bail!("Unexpected action in Character Direction Directive command: {:?}", other)
     };
@@ -262,21 +268,65 @@ fn build_character_movement_directive(character: &str, action: &str, mut inner_a
                 },
                 _ => { bail!("Character move directive needs position argument [\"center\", \"left\", \"right\", \"invisible left\", \"invisible right\"]"); }
             };
-            StageCommand::CharacterChange { character: character.to_string(), operation: CharacterOperation::Move(position) }
+            StageCommand::CharacterChange { character: character.to_string(), operation: CharacterOperation::Move(position), timing: Timing::default() }
         }
         other => bail!("Unexpected action in Character Direction Directive command: {:?}", other)
     };
     Ok(result)
 }
 
+fn build_animation_timing(pair: Pair<Rule>) -> Result<Timing> {
+    ensure!(pair.as_rule() == Rule::animation_timing,
+        "Expected animation timing, found {:?}", pair.as_rule());
+
+    let mut timing = Timing::default();
+    for timing_pair in pair.into_inner() {
+        match timing_pair.as_rule() {
+            Rule::duration => {
+                let duration = timing_pair.as_str();
+                let seconds = match duration.strip_suffix("ms") {
+                    Some(millis) => millis.parse::<f32>().map(|ms| ms / 1000.),
+                    None => duration.trim_end_matches('s').parse::<f32>(),
+                };
+                timing.duration = Some(seconds.with_context(|| format!("Invalid duration '{}'", duration))?);
+            },
+            Rule::animation_easing => {
+                timing.easing = easing_from_name(timing_pair.as_str())
+                    .with_context(|| format!("Unknown easing '{}'", timing_pair.as_str()))?;
+            },
+            other => bail!("Unexpected rule in animation timing: {:?}", other)
+        }
+    }
+    Ok(timing)
+}
+
 pub(crate) fn build_stage_command(pair: Pair<Rule>) -> Result<Statement> {
     ensure!(pair.as_rule() == Rule::stage_command,
         "Expected stage rule, found {:?}", pair.as_rule());
 
-    let command_pair = pair.into_inner().next()
+    let mut commands = Vec::new();
+    for directive_pair in pair.into_inner() {
+        commands.push(build_stage_directive(directive_pair)?);
+    }
+
+    let result = match commands.len() {
+        0 => bail!("Stage command missing inner command"),
+        1 => commands.remove(0),
+        _ => StageCommand::Group(commands),
+    };
+    Ok(Statement::Stage(result))
+}
+
+fn build_stage_directive(pair: Pair<Rule>) -> Result<StageCommand> {
+    ensure!(pair.as_rule() == Rule::stage_directive,
+        "Expected stage directive, found {:?}", pair.as_rule());
+
+    let mut inner = pair.into_inner();
+    let command_pair = inner.next()
         .context("Stage command missing inner command")?;
+    let nowait = inner.next().is_some_and(|p| p.as_rule() == Rule::nowait);
 
-    let result = match command_pair.as_rule() {
+    let mut result = match command_pair.as_rule() {
         Rule::background_change => {
             let mut inner = command_pair.into_inner();
             let background_operation = inner.next().context("Background operation missing")?;
@@ -318,7 +368,12 @@ pub(crate) fn build_stage_command(pair: Pair<Rule>) -> Result<Statement> {
                 _ => { bail!("Invalid background action"); }
             };
 
-            StageCommand::BackgroundChange { operation }
+            let timing = match inner.next() {
+                Some(timing_pair) => build_animation_timing(timing_pair)?,
+                None => Timing::default(),
+            };
+
+            StageCommand::BackgroundChange { operation, timing }
         },
         Rule::gui_change => {
             let mut inner = command_pair.into_inner();
@@ -331,6 +386,7 @@ pub(crate) fn build_stage_command(pair: Pair<Rule>) -> Result<Statement> {
             let gui_target = match gui_element_pair.as_str() {
                 "textbox" => GuiChangeTarget::TextBoxBackground,
                 "namebox" => GuiChangeTarget::NameBoxBackground,
+                "theme" => GuiChangeTarget::Theme,
                 other => bail!("Unknown GUI element: {}", other)
             };
 
@@ -352,6 +408,18 @@ pub(crate) fn build_stage_command(pair: Pair<Rule>) -> Result<Statement> {
                 image_mode,
             }
         },
+        Rule::nvl_change => {
+            let operation_pair = command_pair.into_inner().next()
+                .context("NVL change missing operation")?;
+            let operation = match operation_pair.as_str() {
+                "begins" => NvlOperation::Begin,
+                "ends" => NvlOperation::End,
+                "clears" => NvlOperation::Clear,
+                other => bail!("Unknown NVL operation: {}", other)
+            };
+
+            StageCommand::NvlChange { operation }
+        },
         Rule::scene_change => {
             let expr_pair = command_pair.into_inner().next()
                 .context("Scene change missing expression")?;
@@ -377,17 +445,27 @@ pub(crate) fn build_stage_command(pair: Pair<Rule>) -> Result<Statement> {
             let mut inner_action = action.into_inner().peekable();
             let action = inner_action.next()
                 .context("Character action missing action identifier")?;
-            match action.as_rule() {
+            let mut command = match action.as_rule() {
                 Rule::character_spawn_directive => { build_character_spawn_directive(&character, action.as_str(), inner_action)? }
                 Rule::character_direction_directive => { build_character_direction_directive(&character, action.as_str(), inner_action)? },
                 Rule::character_movement_directive => { build_character_movement_directive(&character, action.as_str(), inner_action)? },
                 other => { bail!("Unexpected rule in character_action {:?}", other); }
+            };
+            if let (Some(timing_pair), StageCommand::CharacterChange { timing, .. }) = (inner_rules.next(), &mut command) {
+                *timing = build_animation_timing(timing_pair)?;
             }
+            command
         },
         other => bail!("Unexpected rule in stage command: {:?}", other)
     };
 
-    Ok(Statement::Stage(result))
+    if nowait {
+        match &mut result {
+            StageCommand::BackgroundChange { timing, .. } | StageCommand::CharacterChange { timing, .. } => timing.nowait = true,
+            other => bail!("'nowait' can only be used with character and background commands, found {:?}", other),
+        }
+    }
+    Ok(result)
 }
 
 pub fn build_code_statement(code_pair: Pair<Rule>) -> Result<Statement> {
@@ -407,6 +485,27 @@ pub fn build_code_statement(code_pair: Pair<Rule>) -> Result<Statement> {
             }
             CodeStatement::Log { exprs }
         },
+        Rule::input => {
+            let mut inner = statement_pair.into_inner();
+            let target = match inner.next().context("Input missing target")?.as_str() {
+                "playername" => InputTarget::PlayerName,
+                variable => InputTarget::Variable(variable.to_owned()),
+            };
+            let prompt_pair = inner.next()
+                .context("Input missing prompt expression")?;
+            let prompt = build_expression(prompt_pair)
+                .context("Failed to build prompt expression for input statement")?;
+            let max_length = match inner.next() {
+                Some(max_pair) => {
+                    let max = max_pair.into_inner().next()
+                        .context("Input max length missing number")?
+                        .as_str();
+                    Some(max.parse::<usize>().context(format!("Input max length '{}' is not a positive integer", max))?)
+                },
+                None => None,
+            };
+            CodeStatement::Input { target, prompt: Box::new(prompt), max_length }
+        },
         other => bail!("Unexpected rule in code statement: {:?}", other)
     };
 
@@ -419,10 +518,13 @@ pub fn build_dialogue(pair: Pair<Rule>) -> Result<Vec<Statement>> {
 
     let mut inner_rules = pair.into_inner().peekable();
 
-    let character = inner_rules.next()
-        .context("Dialogue missing character identifier")?
-        .as_str()
-        .to_owned();
+    let character_pair = inner_rules.next()
+        .context("Dialogue missing character identifier")?;
+    // The player speaks under the name they chose
+    let character = match character_pair.clone().into_inner().next() {
+        Some(identifier) if identifier.as_rule() == Rule::mc_identifier => String::from("[_PLAYERNAME_]"),
+        _ => character_pair.as_str().to_owned(),
+    };
 
     let emotion_statement = match inner_rules.peek() {
         Some(n) if n.as_rule() == Rule::dialogue_emotion_change => {
@@ -436,7 +538,8 @@ pub fn build_dialogue(pair: Pair<Rule>) -> Result<Vec<Statement>> {
 
             Some(Statement::Stage(StageCommand::CharacterChange {
                 character: character.clone(),
-                operation: CharacterOperation::EmotionChange(emotion_name_pair.as_str().to_owned())
+                operation: CharacterOperation::EmotionChange(emotion_name_pair.as_str().to_owned()),
+                timing: Timing::default(),
             }))
         },
         _ => None
@@ -1,4 +1,4 @@
-use crate::chat::controller::InfoTextMessage;
+use crate::chat::controller::{InfoTextMessage, NvlMessage, TextInputMessage};
 use crate::{BackgroundChangeMessage, CharacterSayMessage, GUIChangeMessage, CharacterChangeMessage, VisualNovelState};
 use crate::compiler::ast::{CodeStatement, Dialogue, Evaluate, InfoText, StageCommand, Statement, TextItem};
 use bevy::prelude::*;
@@ -15,7 +15,7 @@ pub struct ActChangeMessage {
     pub act_id: String
 }
 
-pub struct InvokeContext<'l, 'a, 'b, 'd, 'e, 'f, 'g, 'h, 'i> {
+pub struct InvokeContext<'l, 'a, 'b, 'd, 'e, 'f, 'g, 'h, 'i, 'j, 'k> {
     pub game_state: &'l mut ResMut<'a, VisualNovelState>,
     pub character_say_message: &'l mut MessageWriter<'b, CharacterSayMessage>,
     pub background_change_message: &'l mut MessageWriter<'d, BackgroundChangeMessage>,
@@ -24,6 +24,25 @@ pub struct InvokeContext<'l, 'a, 'b, 'd, 'e, 'f, 'g, 'h, 'i> {
     pub act_change_message: &'l mut MessageWriter<'g, ActChangeMessage>,
     pub character_change_message: &'l mut MessageWriter<'h, CharacterChangeMessage>,
     pub info_text_message: &'l mut MessageWriter<'i, InfoTextMessage>,
+    pub nvl_message: &'l mut MessageWriter<'j, NvlMessage>,
+    pub text_input_message: &'l mut MessageWriter<'k, TextInputMessage>,
+}
+impl<'a, 'b, 'd, 'e, 'f, 'g, 'h, 'i, 'j, 'k> InvokeContext<'_, 'a, 'b, 'd, 'e, 'f, 'g, 'h, 'i, 'j, 'k> {
+    /// Borrows the context again, so that it can be used to invoke several statements
+    pub fn reborrow(&mut self) -> InvokeContext<'_, 'a, 'b, 'd, 'e, 'f, 'g, 'h, 'i, 'j, 'k> {
+        InvokeContext {
+            game_state: self.game_state,
+            character_say_message: self.character_say_message,
+            background_change_message: self.background_change_message,
+            gui_change_message: self.gui_change_message,
+            scene_change_message: self.scene_change_message,
+            act_change_message: self.act_change_message,
+            character_change_message: self.character_change_message,
+            info_text_message: self.info_text_message,
+            nvl_message: self.nvl_message,
+            text_input_message: self.text_input_message,
+        }
+    }
 }
 pub trait Invoke {
     fn invoke ( &self, ctx: InvokeContext ) -> Result<()>;
@@ -32,14 +51,18 @@ impl Invoke for Dialogue {
     fn invoke( &self, ctx: InvokeContext ) -> Result<()> {
         let dialogue = self.dialogue.evaluate_into_string()
             .context("...while evaluating Dialogue expression")?;
+        let dialogue = ctx.game_state.interpolate(&dialogue);
         info!("Invoking Dialogue::Say");
 
-        ctx.character_say_message.write(CharacterSayMessage {
-            name: self.character.to_owned(),
-            message: dialogue
-        });
+        // Lines gone over when rewinding several of them at once are not shown again
+        if ctx.game_state.rewinding == 0 {
+            ctx.character_say_message.write(CharacterSayMessage {
+                name: self.character.to_owned(),
+                message: dialogue
+            });
 
-        ctx.game_state.blocking = true;
+            ctx.game_state.blocking = true;
+        }
 
         Ok(())
     }
@@ -48,6 +71,7 @@ impl Invoke for InfoText {
     fn invoke ( &self, ctx: InvokeContext ) -> Result<()> {
         let text = self.infotext.evaluate_into_string()
             .context("...while evaluating InfoText expression")?;
+        let text = ctx.game_state.interpolate(&text);
         info!("Invoking InfoText");
         
         
@@ -69,10 +93,11 @@ impl Invoke for InfoText {
 impl Invoke for StageCommand {
     fn invoke( &self, ctx: InvokeContext ) -> Result<()> {
         match self {
-            StageCommand::BackgroundChange { operation } => {
+            StageCommand::BackgroundChange { operation, timing } => {
                 info!("Invoking StageCommand::BackgroundChange to {:?}", operation);
                 ctx.background_change_message.write(BackgroundChangeMessage {
                     operation: operation.clone(),
+                    timing: *timing,
                 });
             },
             StageCommand::GUIChange { gui_target, sprite_expr, image_mode } => {
@@ -88,6 +113,12 @@ impl Invoke for StageCommand {
                     image_mode,
                 });
             },
+            StageCommand::NvlChange { operation } => {
+                info!("Invoking StageCommand::NvlChange {:?}", operation);
+                ctx.nvl_message.write(NvlMessage {
+                    operation: operation.clone(),
+                });
+            },
             StageCommand::SceneChange { scene_expr } => {
                 let scene_id = scene_expr.evaluate_into_string()
                     .context("...while evaluating SceneChange expression")?;
@@ -106,16 +137,21 @@ impl Invoke for StageCommand {
                     act_id
                 });
             },
-            StageCommand::CharacterChange { character, operation } => {
+            StageCommand::CharacterChange { character, operation, timing } => {
                 info!("Invoking StageCommand::CharacterChange to {} of type {:?}", character, operation);
-                let message = CharacterChangeMessage {
+                ctx.character_change_message.write(CharacterChangeMessage {
                     character: character.clone(),
-                    operation: operation.clone()
-                };
-                if message.is_blocking() {
-                    ctx.game_state.blocking = true;
+                    operation: operation.clone(),
+                    timing: *timing,
+                });
+            },
+            StageCommand::Group(commands) => {
+                info!("Invoking StageCommand::Group of {} commands", commands.len());
+                let mut ctx = ctx;
+                for command in commands {
+                    command.invoke(ctx.reborrow())
+                        .context("...while invoking grouped stage command")?;
                 }
-                ctx.character_change_message.write(message);
             }
         }
         
@@ -123,7 +159,7 @@ impl Invoke for StageCommand {
     }
 }
 impl Invoke for CodeStatement {
-    fn invoke( &self, _ctx: InvokeContext ) -> Result<()> {
+    fn invoke( &self, ctx: InvokeContext ) -> Result<()> {
         match self {
             CodeStatement::Log { exprs } => {
                 let mut log_parts: Vec<String> = Vec::new();
@@ -137,6 +173,20 @@ impl Invoke for CodeStatement {
                 let log_message = log_parts.join(" ");
                 println!("[ Log ] {}", log_message);
 
+                Ok(())
+            },
+            CodeStatement::Input { target, prompt, max_length } => {
+                let prompt = prompt.evaluate_into_string()
+                    .context("...while evaluating Input prompt expression")?;
+                info!("Invoking CodeStatement::Input for {:?}", target);
+
+                ctx.text_input_message.write(TextInputMessage {
+                    target: target.clone(),
+                    prompt: ctx.game_state.interpolate(&prompt),
+                    max_length: *max_length,
+                });
+                ctx.game_state.blocking = true;
+
                 Ok(())
             },
         }
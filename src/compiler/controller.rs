@@ -1,19 +1,24 @@
 use crate::character::CharacterChangeMessage;
-use crate::chat::controller::InfoTextMessage;
+use crate::chat::controller::{InfoTextMessage, NvlMessage, TextInputMessage};
 use crate::compiler::ast::Statement;
 use crate::compiler::calling::{Invoke, InvokeContext, SceneChangeMessage, ActChangeMessage};
-use crate::{Cursor, HistoryItem, SabiEnd, ast};
-use crate::{BackgroundChangeMessage, CharacterSayMessage, GUIChangeMessage, SabiStart, ScriptId, VisualNovelState};
+use crate::compiler::fast_forward::{StageSnapshot, preceding_statements};
+use crate::compiler::manifest::{ActManifest, ActManifests};
+use crate::error::{SabiErrors, report_errors};
+use crate::tween::BlockingAnimations;
+use crate::{ActEntered, ChoiceMade, Cursor, HistoryItem, LogExported, LogFormat, SabiEnd, SabiExportLog, SabiJump, SabiPause, SabiResume, SabiStop, SceneEntered, ast};
+use crate::{BackgroundChangeMessage, CharacterSayMessage, GUIChangeMessage, SabiSettings, SabiStart, ScriptId, UserDefinedConstants, VisualNovelState};
 
 use std::collections::HashMap;
+use std::fs;
 use std::path::PathBuf;
-use bevy::asset::{LoadState, LoadedFolder};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use bevy::asset::{AssetLoadFailedEvent, LoadState, LoadedFolder};
 use bevy::color::palettes::css::{BLACK, WHITE};
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use anyhow::{Context, Result};
 
-const SCRIPTS_ASSET_PATH: &str = "sabi/acts";
-
 /* States */
 #[derive(States, Debug, Default, Clone, Copy, Hash, Eq, PartialEq)]
 pub enum SabiState {
@@ -23,6 +28,24 @@ pub enum SabiState {
     Running,
 }
 
+/// Sub state of [SabiState::Running] used to freeze the current run.
+#[derive(SubStates, Debug, Default, Clone, Copy, Hash, Eq, PartialEq)]
+#[source(SabiState = SabiState::Running)]
+pub enum SabiRunState {
+    #[default]
+    Playing,
+    Paused,
+}
+
+/// Sets ordering the systems which run the script, so that stage commands are handled in the frame they are invoked
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum SabiSystems {
+    /// Invokes the next statement
+    Run,
+    /// Controllers handling the messages written by the invoked statements
+    Stage,
+}
+
 #[derive(Resource, Default)]
 struct ControllersReady {
     pub background_controller: bool,
@@ -47,6 +70,8 @@ impl ControllersReady {
 /* Components */
 #[derive(Component)]
 pub struct UiRoot;
+#[derive(Component)]
+struct LoadingText;
 
 /* Messages */
 #[derive(Message)]
@@ -55,6 +80,7 @@ pub struct ControllersSetStateMessage(pub SabiState);
 pub struct ControllerReadyMessage(pub Controller);
 
 /* Custom Types */
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub enum Controller {
     Background,
     Character,
@@ -68,21 +94,92 @@ struct HandleToScriptsFolder(Handle<LoadedFolder>);
 struct ScriptsResource(ScriptsMap);
 type ScriptsMap = HashMap<ScriptId, Handle<ast::Act>>;
 #[derive(Resource)]
-struct CurrentScript(pub ScriptId);
+pub(crate) struct CurrentScript(pub ScriptId);
+/// Scene and statement the run begins from, when it does not start at the act entrypoint
+#[derive(Resource)]
+struct StartPosition {
+    scene: String,
+    statement: usize,
+}
+/// Loaded, failed and total amount of assets each [Controller] is waiting for
+#[derive(Resource, Default)]
+pub(crate) struct LoadingProgress(pub HashMap<Controller, (usize, usize, usize)>);
+
+/* System Params */
+/// Read-only access to the progress of the current run.
+#[derive(SystemParam)]
+pub struct SabiRuntime<'w> {
+    state: Res<'w, State<SabiState>>,
+    run_state: Option<Res<'w, State<SabiRunState>>>,
+    game_state: Res<'w, VisualNovelState>,
+    current_script: Option<Res<'w, CurrentScript>>,
+}
+
+impl SabiRuntime<'_> {
+    pub fn state(&self) -> SabiState {
+        *self.state.get()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.run_state.as_ref().is_some_and(|s| *s.get() == SabiRunState::Paused)
+    }
+
+    /// [ScriptId] of the act being run, [None] when sabi is idle
+    pub fn script(&self) -> Option<&ScriptId> {
+        if self.state() == SabiState::Idle {
+            return None;
+        }
+        self.current_script.as_ref().map(|s| &s.0)
+    }
+
+    /// Name of the scene being run, [None] when no scene has started yet
+    pub fn scene(&self) -> Option<&str> {
+        if self.state() != SabiState::Running || self.game_state.scene.name.is_empty() {
+            return None;
+        }
+        Some(self.game_state.scene.name.as_str())
+    }
+
+    /// Index of the last invoked statement in the current scene
+    pub fn statement_index(&self) -> Option<usize> {
+        if self.state() != SabiState::Running {
+            return None;
+        }
+        self.game_state.statements.position()
+    }
+
+    /// Log of the current run, as written by [SabiExportLog]
+    pub fn transcript(&self, format: LogFormat) -> String {
+        self.game_state.transcript(format)
+    }
+}
 
 pub struct Compiler;
 impl Plugin for Compiler {
     fn build(&self, app: &mut App) {
         app
             .init_state::<SabiState>()
+            .add_sub_state::<SabiRunState>()
             .init_resource::<ControllersReady>()
             .init_resource::<ScriptsResource>()
+            .init_resource::<LoadingProgress>()
+            .init_resource::<BlockingAnimations>()
+            .configure_sets(Update, SabiSystems::Run.before(SabiSystems::Stage))
             .add_message::<ControllerReadyMessage>()
             .add_message::<ControllersSetStateMessage>()
             .add_message::<SceneChangeMessage>()
             .add_message::<ActChangeMessage>()
             .add_message::<SabiStart>()
             .add_message::<SabiEnd>()
+            .add_message::<SabiPause>()
+            .add_message::<SabiResume>()
+            .add_message::<SabiStop>()
+            .add_message::<SabiJump>()
+            .add_message::<ActEntered>()
+            .add_message::<SceneEntered>()
+            .add_message::<ChoiceMade>()
+            .add_message::<SabiExportLog>()
+            .add_message::<LogExported>()
             .add_systems(OnEnter(SabiState::Idle), (clean_states, propagate_state).chain())
             .add_systems(Update, check_start.run_if(in_state(SabiState::Idle)))
             .add_systems(OnExit(SabiState::Idle), spawn_ui_root)
@@ -91,20 +188,115 @@ impl Plugin for Compiler {
                     propagate_state,
                     import_scripts_folder
                 ).chain())
-            .add_systems(Update, check_states.run_if(in_state(SabiState::WaitingForControllers)))
-            .add_systems(OnEnter(SabiState::Running), trigger_running_controllers)
-            .add_systems(Update, (run, handle_scene_changes, handle_act_changes).run_if(in_state(SabiState::Running)));
+            .add_systems(Update, (check_states.pipe(report_errors), update_loading_text).run_if(in_state(SabiState::WaitingForControllers)))
+            .add_systems(OnEnter(SabiState::Running), trigger_running_controllers.pipe(report_errors))
+            .add_systems(Update, run.pipe(report_errors).in_set(SabiSystems::Run).run_if(in_state(SabiRunState::Playing)))
+            .add_systems(Update, (handle_scene_changes.pipe(report_errors), handle_act_changes.pipe(report_errors), handle_jumps.pipe(report_errors)).run_if(in_state(SabiState::Running)))
+            .add_systems(Update, (handle_runtime_controls, (record_choices, export_log).chain()).run_if(not(in_state(SabiState::Idle))))
+            .add_systems(Update, reload_scripts.run_if(in_state(SabiState::Running)))
+            .add_systems(Update, report_script_failures);
     }
 }
 fn clean_states(
+    mut commands: Commands,
     mut controllers_state: ResMut<ControllersReady>,
+    mut visual_novel_state: ResMut<VisualNovelState>,
+    mut loading_progress: ResMut<LoadingProgress>,
+    mut blocking_animations: ResMut<BlockingAnimations>,
 ) {
     controllers_state.reset();
+    *visual_novel_state = VisualNovelState::default();
+    loading_progress.0.clear();
+    blocking_animations.reset();
+    commands.remove_resource::<ActManifests>();
+}
+/// Keeps the options picked by the player in the history, for the transcripts
+fn record_choices(
+    mut choice_reader: MessageReader<ChoiceMade>,
+    mut game_state: ResMut<VisualNovelState>,
+) {
+    for choice in choice_reader.read() {
+        game_state.record_choice(&choice.text);
+    }
+}
+/// Writes the transcript asked by [SabiExportLog] to the `logs` folder of the data path
+fn export_log(
+    mut export_reader: MessageReader<SabiExportLog>,
+    mut exported_writer: MessageWriter<LogExported>,
+    game_state: Res<VisualNovelState>,
+    settings: Res<SabiSettings>,
+) {
+    for export in export_reader.read() {
+        let dir = settings.data_path().map(|dir| dir.join("logs"));
+        let written = dir.context("No user data directory to write the log in").and_then(|dir| {
+            fs::create_dir_all(&dir).with_context(|| format!("Could not create {}", dir.display()))?;
+            let name = format!("{}_{}", game_state.act.name, timestamp(SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default()));
+            // Numbered so that exports within the same second do not overwrite each other
+            let mut path = dir.join(format!("{}.{}", name, export.format.extension()));
+            let mut number = 1;
+            while path.exists() {
+                number += 1;
+                path = dir.join(format!("{}_{}.{}", name, number, export.format.extension()));
+            }
+            fs::write(&path, game_state.transcript(export.format)).with_context(|| format!("Could not write {}", path.display()))?;
+            Ok(path)
+        });
+        match written {
+            Ok(path) => {
+                info!("[ Exported log to {} ]", path.display());
+                exported_writer.write(LogExported { path });
+            },
+            Err(e) => warn!("Failed to export the log: {:#}", e),
+        }
+    }
+}
+/// UTC time `since_epoch` as `YYYY-MM-DD_HH-MM-SS`, safe to use in file names
+fn timestamp(since_epoch: Duration) -> String {
+    let secs = since_epoch.as_secs() as i64;
+    let (days, time) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+    // Civil date from days since 1970-01-01, after Howard Hinnant's `civil_from_days`
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}_{:02}-{:02}-{:02}", year, month, day, time / 3600, time % 3600 / 60, time % 60)
+}
+/// Builds the manifest of the given act, preloading the assets of the acts it can change to
+fn act_manifests(
+    script: &ScriptId,
+    scripts_resource: &ScriptsResource,
+    acts: &Assets<ast::Act>,
+) -> Result<ActManifests> {
+    let act = scripts_resource.0.get(script)
+        .and_then(|handle| acts.get(handle))
+        .context(format!("Could not find act {:?}", script))?;
+    let required = ActManifest::from_act(act)?;
+    let mut preload = ActManifest::default();
+    for next_act in &required.next_acts {
+        let next_script = ScriptId { chapter: script.chapter.clone(), act: next_act.clone() };
+        if let Some(next_act) = scripts_resource.0.get(&next_script).and_then(|handle| acts.get(handle)) {
+            preload.extend(ActManifest::from_act(next_act)?);
+        }
+    }
+    Ok(ActManifests { required, preload })
 }
 fn trigger_running_controllers(
+    mut commands: Commands,
     mut msg_writer: MessageWriter<ControllersSetStateMessage>,
+    mut act_entered: MessageWriter<ActEntered>,
+    mut scene_entered: MessageWriter<SceneEntered>,
+    mut background_change_message: MessageWriter<BackgroundChangeMessage>,
+    mut character_change_message: MessageWriter<CharacterChangeMessage>,
+    mut gui_change_message: MessageWriter<GUIChangeMessage>,
+    mut nvl_message: MessageWriter<NvlMessage>,
     mut visual_novel_state: ResMut<VisualNovelState>,
     current_script: Res<CurrentScript>,
+    start_position: Option<Res<StartPosition>>,
     scripts_resource: Res<ScriptsResource>,
     acts: Res<Assets<ast::Act>>,
 ) -> Result<(), BevyError> {
@@ -113,15 +305,35 @@ fn trigger_running_controllers(
     let act = acts.get(act_handle.id())
         .context("Could not find script element")?;
 
+    let (scene_id, statement) = match &start_position {
+        Some(start) => (&start.scene, start.statement),
+        None => (&act.entrypoint, 0),
+    };
+    let scene = act.scenes.get(scene_id)
+        .context(format!("Scene '{}' not found in act '{}'", scene_id, act.name))?
+        .clone();
+    if statement > scene.statements.len() {
+        warn!("Scene '{}' has only {} statements, starting from its end", scene_id, scene.statements.len());
+    }
+
+    if start_position.is_some() {
+        let preceding = preceding_statements(act, scene_id, statement);
+        info!("[ Fast-forwarding {} statements to scene '{}', statement {} ]", preceding.len(), scene_id, statement);
+        StageSnapshot::from_statements(preceding.into_iter())
+            .write_messages(&mut background_change_message, &mut character_change_message, &mut gui_change_message, &mut nvl_message);
+        commands.remove_resource::<StartPosition>();
+    }
+
     visual_novel_state.act = Box::new(act.clone());
-    visual_novel_state.statements = Cursor::new(act.scenes.get(&act.entrypoint)
-        .context("Error retrieving act entrypoint")?
-        .statements.clone());
-    visual_novel_state.history.push(HistoryItem::Descriptor(format!("Act: {}\n", act.name)));
-    visual_novel_state.history.push(HistoryItem::Descriptor(format!("Scene: {}\n", act.entrypoint)));
+    visual_novel_state.statements = Cursor::with_position(scene.statements.clone(), statement as i32 - 1);
+    visual_novel_state.scene = scene;
+    visual_novel_state.history.push(HistoryItem::Act(act.name.clone()));
+    visual_novel_state.history.push(HistoryItem::Scene(scene_id.clone()));
     visual_novel_state.blocking = false;
 
     msg_writer.write(ControllersSetStateMessage(SabiState::Running));
+    act_entered.write(ActEntered(current_script.0.clone()));
+    scene_entered.write(SceneEntered { act: act.name.clone(), scene: scene_id.clone() });
     Ok(())
 }
 fn propagate_state(
@@ -133,23 +345,32 @@ fn propagate_state(
 fn check_start(
     mut commands: Commands,
     mut state: ResMut<NextState<SabiState>>,
+    mut visual_novel_state: ResMut<VisualNovelState>,
+    user_defined_constants: Res<UserDefinedConstants>,
     mut msg_reader: MessageReader<SabiStart>
 ) {
     for msg in msg_reader.read() {
-        let script_id = msg.0.clone();
+        visual_novel_state.playername = user_defined_constants.playername.clone();
+        let script_id = msg.script.clone();
         commands.insert_resource(CurrentScript(script_id));
+        match &msg.scene {
+            Some(scene) => commands.insert_resource(StartPosition { scene: scene.clone(), statement: msg.statement }),
+            None => commands.remove_resource::<StartPosition>(),
+        }
         state.set(SabiState::WaitingForControllers);
     }
 }
 fn import_scripts_folder(
     mut commands: Commands,
-    asset_server: Res<AssetServer>
+    asset_server: Res<AssetServer>,
+    settings: Res<SabiSettings>,
 ) {
-    let loaded_folder = asset_server.load_folder(SCRIPTS_ASSET_PATH);
+    let loaded_folder = asset_server.load_folder(&settings.scripts_path);
     commands.insert_resource(HandleToScriptsFolder(loaded_folder));
 }
 fn spawn_ui_root(
     mut commands: Commands,
+    settings: Res<SabiSettings>,
 ) {
     commands.spawn((
         Node {
@@ -180,24 +401,26 @@ fn spawn_ui_root(
                         TextFont {
                             font_size: 40.,
                             ..default()
-                        }
+                        },
+                        LoadingText
                     )
                 ],
-                ZIndex(100),
+                ZIndex(settings.layers.loading),
                 DespawnOnExit(SabiState::WaitingForControllers)
             )
         ]
     ));
 }
 fn define_script_entry(
-    handle: Handle<ast::Act>
+    handle: Handle<ast::Act>,
+    scripts_path: &str,
 ) -> Result<(ScriptId, Handle<ast::Act>), BevyError> {
     let path = match handle.path() {
         Some(asset_path) => asset_path.path(),
         None => { return Err(anyhow::anyhow!("Error retrieving script path").into()) }
     };
     
-    let expected_len = PathBuf::from(SCRIPTS_ASSET_PATH).iter().count() + 2;
+    let expected_len = PathBuf::from(scripts_path).iter().count() + 2;
 
     let script_id = if path.iter().count() == expected_len {
         let chapter = path.components().nth(expected_len - 2)
@@ -213,7 +436,23 @@ fn define_script_entry(
     };
     Ok((script_id, handle))
 }
+fn update_loading_text(
+    loading_progress: Res<LoadingProgress>,
+    mut loading_text: Single<&mut Text, With<LoadingText>>,
+) {
+    if !loading_progress.is_changed() {
+        return;
+    }
+    let (loaded, failed, total) = loading_progress.0.values()
+        .fold((0, 0, 0), |acc, (loaded, failed, total)| (acc.0 + loaded, acc.1 + failed, acc.2 + total));
+    loading_text.0 = match ((loaded + failed) * 100).checked_div(total) {
+        Some(percentage) if failed > 0 => format!("Loading {}% ({} failed)", percentage, failed),
+        Some(percentage) => format!("Loading {}%", percentage),
+        None => String::from("Loading"),
+    };
+}
 fn check_states(
+    mut commands: Commands,
     mut msg_controller_reader: MessageReader<ControllerReadyMessage>,
     mut controllers_state: ResMut<ControllersReady>,
     mut sabi_state: ResMut<NextState<SabiState>>,
@@ -221,6 +460,9 @@ fn check_states(
     folder_handle: Res<HandleToScriptsFolder>,
     loaded_folders: Res<Assets<LoadedFolder>>,
     mut scripts_resource: ResMut<ScriptsResource>,
+    current_script: Res<CurrentScript>,
+    acts: Res<Assets<ast::Act>>,
+    settings: Res<SabiSettings>,
 ) -> Result<(), BevyError> {
     if !controllers_state.compiler_controller {
         if let Some(state) = asset_server.get_load_state(folder_handle.0.id()) {
@@ -228,10 +470,11 @@ fn check_states(
                 LoadState::Loaded => {
                     if let Some(loaded_folder) = loaded_folders.get(folder_handle.0.id()) {
                         for handle in &loaded_folder.handles {
-                            let (script_id, entry) = define_script_entry(handle.clone().typed())?;
+                            let (script_id, entry) = define_script_entry(handle.clone().typed(), &settings.scripts_path)?;
                             scripts_resource.0.insert(script_id, entry);
                         }
                         info!("Resource complete: {:?}", scripts_resource.0);
+                        commands.insert_resource(act_manifests(&current_script.0, &scripts_resource, &acts)?);
                         controllers_state.compiler_controller = true;
                     } else {
                         return Err(anyhow::anyhow!("Could not find script file loaded folder!").into());
@@ -258,7 +501,7 @@ fn check_states(
     }
     Ok(())
 }
-fn run<'a, 'b, 'c, 'd, 'e, 'f, 'g, 'h> (
+fn run<'a, 'b, 'c, 'd, 'e, 'f, 'g, 'h, 'i, 'j> (
     mut game_state: ResMut<'a, VisualNovelState>,
     mut character_say_message: MessageWriter<'b, CharacterSayMessage>,
     mut background_change_message: MessageWriter<'c, BackgroundChangeMessage>,
@@ -267,6 +510,8 @@ fn run<'a, 'b, 'c, 'd, 'e, 'f, 'g, 'h> (
     mut act_change_message: MessageWriter<'f, ActChangeMessage>,
     mut character_change_message: MessageWriter<'g, CharacterChangeMessage>,
     mut info_text_message: MessageWriter<'h, InfoTextMessage>,
+    mut nvl_message: MessageWriter<'i, NvlMessage>,
+    mut text_input_message: MessageWriter<'j, TextInputMessage>,
 
     mut state: ResMut<NextState<SabiState>>,
     mut ev_controller_writer: MessageWriter<ControllersSetStateMessage>,
@@ -296,7 +541,7 @@ fn run<'a, 'b, 'c, 'd, 'e, 'f, 'g, 'h> (
     } else {
         let next_statement = game_state.statements.next();
         if let Some(stm) = &next_statement {
-            game_state.history.push(HistoryItem::Statement(stm.clone()));
+            game_state.record(stm)?;
         }
         next_statement
     };
@@ -311,6 +556,8 @@ fn run<'a, 'b, 'c, 'd, 'e, 'f, 'g, 'h> (
                 act_change_message: &mut act_change_message,
                 character_change_message: &mut character_change_message,
                 info_text_message: &mut info_text_message,
+                nvl_message: &mut nvl_message,
+                text_input_message: &mut text_input_message,
             })
             .context("Failed to invoke statement")?;
     } else {
@@ -325,6 +572,7 @@ fn run<'a, 'b, 'c, 'd, 'e, 'f, 'g, 'h> (
 fn handle_scene_changes(
     mut scene_change_messages: MessageReader<SceneChangeMessage>,
     mut game_state: ResMut<VisualNovelState>,
+    mut scene_entered: MessageWriter<SceneEntered>,
 ) -> Result<(), BevyError> {
     for msg in scene_change_messages.read() {
         let new_scene = game_state.act.scenes.get(&msg.scene_id)
@@ -334,8 +582,9 @@ fn handle_scene_changes(
         info!("Changing to scene: {}", msg.scene_id);
         game_state.scene = new_scene.clone();
         game_state.statements = Cursor::new(game_state.scene.statements.clone());
-        game_state.history.push(HistoryItem::Descriptor(format!("Scene {}", new_scene.name)));
+        game_state.history.push(HistoryItem::Scene(new_scene.name.clone()));
         game_state.blocking = false;
+        scene_entered.write(SceneEntered { act: game_state.act.name.clone(), scene: msg.scene_id.clone() });
         info!("[ Scene changed to '{}' ]", msg.scene_id);
     }
 
@@ -347,6 +596,9 @@ fn handle_act_changes(
     mut current_script: ResMut<CurrentScript>,
     scripts_resource: Res<ScriptsResource>,
     scripts_assets: Res<Assets<ast::Act>>,
+    mut act_entered: MessageWriter<ActEntered>,
+    mut scene_entered: MessageWriter<SceneEntered>,
+    mut manifests: ResMut<ActManifests>,
 ) -> Result<(), BevyError> {
     for msg in act_change_messages.read() {
         current_script.0.act = msg.act_id.clone();
@@ -362,10 +614,151 @@ fn handle_act_changes(
         game_state.act = Box::new(act.clone());
         game_state.scene = entrypoint_scene;
         game_state.statements = Cursor::new(game_state.scene.statements.clone());
-        game_state.history.push(HistoryItem::Descriptor(format!("Act {}", act.name)));
+        game_state.history.push(HistoryItem::Act(act.name.clone()));
+        game_state.history.push(HistoryItem::Scene(act.entrypoint.clone()));
         game_state.blocking = false;
+        *manifests = act_manifests(&current_script.0, &scripts_resource, &scripts_assets)?;
+        act_entered.write(ActEntered(current_script.0.clone()));
+        scene_entered.write(SceneEntered { act: act.name.clone(), scene: act.entrypoint.clone() });
         info!("[ Act changed to '{}' ]", msg.act_id);
     }
 
     Ok(())
 }
+fn handle_runtime_controls(
+    mut pause_messages: MessageReader<SabiPause>,
+    mut resume_messages: MessageReader<SabiResume>,
+    mut stop_messages: MessageReader<SabiStop>,
+    mut run_state: ResMut<NextState<SabiRunState>>,
+    mut sabi_state: ResMut<NextState<SabiState>>,
+) {
+    if pause_messages.read().count() > 0 {
+        info!("[ Run paused ]");
+        run_state.set(SabiRunState::Paused);
+    }
+    if resume_messages.read().count() > 0 {
+        info!("[ Run resumed ]");
+        run_state.set(SabiRunState::Playing);
+    }
+    if stop_messages.read().count() > 0 {
+        info!("[ Run stopped ]");
+        sabi_state.set(SabiState::Idle);
+    }
+}
+fn handle_jumps(
+    mut jump_messages: MessageReader<SabiJump>,
+    mut game_state: ResMut<VisualNovelState>,
+    mut current_script: ResMut<CurrentScript>,
+    scripts_resource: Res<ScriptsResource>,
+    scripts_assets: Res<Assets<ast::Act>>,
+    mut act_entered: MessageWriter<ActEntered>,
+    mut scene_entered: MessageWriter<SceneEntered>,
+    mut manifests: ResMut<ActManifests>,
+) -> Result<(), BevyError> {
+    for msg in jump_messages.read() {
+        let act_handle = scripts_resource.0.get(&msg.script).context(format!("Could not find act handle for {:?}", msg.script))?;
+        let act = scripts_assets.get(act_handle).context(format!("Could not find act {:?}", act_handle))?;
+        let scene_id = msg.scene.as_ref().unwrap_or(&act.entrypoint);
+        let scene = act.scenes.get(scene_id)
+            .context(format!("Scene '{}' not found in act '{}'", scene_id, msg.script.act))?
+            .clone();
+
+        info!("Jumping to act '{}', scene '{}'", msg.script.act, scene_id);
+
+        current_script.0 = msg.script.clone();
+        *game_state.act = act.clone();
+        game_state.scene = scene;
+        game_state.statements = Cursor::new(game_state.scene.statements.clone());
+        game_state.history.push(HistoryItem::Act(act.name.clone()));
+        game_state.history.push(HistoryItem::Scene(scene_id.clone()));
+        game_state.rewinding = 0;
+        game_state.blocking = false;
+        *manifests = act_manifests(&msg.script, &scripts_resource, &scripts_assets)?;
+        act_entered.write(ActEntered(msg.script.clone()));
+        scene_entered.write(SceneEntered { act: act.name.clone(), scene: scene_id.clone() });
+        info!("[ Jumped to '{}' ]", scene_id);
+    }
+
+    Ok(())
+}
+/// Swaps the running [ast::Act] when its script is modified on disk, keeping the player
+/// in the same scene and on the nearest equivalent statement
+fn reload_scripts(
+    mut asset_messages: MessageReader<AssetEvent<ast::Act>>,
+    mut game_state: ResMut<VisualNovelState>,
+    current_script: Res<CurrentScript>,
+    scripts_resource: Res<ScriptsResource>,
+    acts: Res<Assets<ast::Act>>,
+    mut manifests: ResMut<ActManifests>,
+    mut errors: ResMut<SabiErrors>,
+) {
+    let Some(current_handle) = scripts_resource.0.get(&current_script.0) else {
+        return;
+    };
+    for msg in asset_messages.read() {
+        let AssetEvent::Modified { id } = msg else {
+            continue;
+        };
+        if *id != current_handle.id() {
+            continue;
+        }
+        let Some(act) = acts.get(*id) else {
+            continue;
+        };
+
+        let scene = match act.scenes.get(&game_state.scene.name) {
+            Some(scene) => scene.clone(),
+            None => {
+                warn!("Scene '{}' was removed, restarting from entrypoint '{}'", game_state.scene.name, act.entrypoint);
+                match act.scenes.get(&act.entrypoint) {
+                    Some(scene) => scene.clone(),
+                    None => continue,
+                }
+            }
+        };
+
+        // Prefer an identical statement close to the current one, falling back to the same index
+        let old_position = game_state.statements.position();
+        let current = game_state.statements.current();
+        let position = match (old_position, current) {
+            (Some(old), Some(current)) => scene.statements.iter()
+                .enumerate()
+                .filter(|(_, s)| *s == current)
+                .min_by_key(|(index, _)| index.abs_diff(old))
+                .map(|(index, _)| index as i32)
+                .unwrap_or(old as i32),
+            _ => -1,
+        };
+
+        match act_manifests(&current_script.0, &scripts_resource, &acts) {
+            Ok(new_manifests) => *manifests = new_manifests,
+            Err(e) => errors.report(format!("{:?}", e)),
+        }
+        *game_state.act = act.clone();
+        game_state.statements = Cursor::with_position(scene.statements.clone(), position);
+        game_state.scene = scene;
+        info!("[ Reloaded act '{}' at scene '{}', statement {} ]", act.name, game_state.scene.name, position);
+    }
+}
+/// Forwards script parsing failures to the error policy instead of only logging them
+fn report_script_failures(
+    mut failed_messages: MessageReader<AssetLoadFailedEvent<ast::Act>>,
+    mut errors: ResMut<SabiErrors>,
+) {
+    for msg in failed_messages.read() {
+        errors.report(format!("Failed to load script '{}': {}", msg.path, msg.error));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamp_formats_utc_dates() {
+        assert_eq!(timestamp(Duration::ZERO), "1970-01-01_00-00-00");
+        assert_eq!(timestamp(Duration::from_secs(951782400)), "2000-02-29_00-00-00");
+        assert_eq!(timestamp(Duration::from_secs(1709251199)), "2024-02-29_23-59-59");
+        assert_eq!(timestamp(Duration::from_millis(4_102_448_461_500)), "2100-01-01_01-01-01");
+    }
+}
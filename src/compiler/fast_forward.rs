@@ -0,0 +1,171 @@
+use std::collections::HashSet;
+use bevy::prelude::*;
+
+use crate::{
+    BackgroundChangeMessage, CharacterChangeMessage, GUIChangeMessage,
+    background::controller::BackgroundOperation,
+    character::controller::{CharacterDirection, CharacterPosition, SpawnInfo},
+    character::CharacterOperation,
+    chat::controller::{GuiChangeTarget, GuiImageMode, NvlMessage, NvlOperation},
+    compiler::ast::{Act, Evaluate, StageCommand, Statement},
+    tween::Timing
+};
+
+/// State of a character at the end of a fast-forward
+#[derive(Debug)]
+struct CharacterSnapshot {
+    name: String,
+    emotion: Option<String>,
+    position: CharacterPosition,
+    direction: Option<CharacterDirection>,
+}
+
+/// Stage reached by running a sequence of statements, collapsed so that it can be applied
+/// at once, skipping text and animations.
+#[derive(Debug, Default)]
+pub(crate) struct StageSnapshot {
+    background: Option<String>,
+    /// Characters on stage, in spawn order
+    characters: Vec<CharacterSnapshot>,
+    gui: Vec<(GuiChangeTarget, String, GuiImageMode)>,
+    /// Whether the text is shown in NVL mode, on an empty page
+    nvl: bool,
+}
+
+impl StageSnapshot {
+    pub fn from_statements<'a>(statements: impl Iterator<Item = &'a Statement>) -> Self {
+        let mut snapshot = StageSnapshot::default();
+        for statement in statements {
+            if let Statement::Stage(stage) = statement {
+                snapshot.apply(stage);
+            }
+        }
+        snapshot
+    }
+
+    fn apply(&mut self, stage: &StageCommand) {
+        match stage {
+            StageCommand::BackgroundChange { operation, .. } => match operation {
+                BackgroundOperation::ChangeTo(target) => self.background = Some(target.clone()),
+                BackgroundOperation::DissolveTo(target) => self.background = target.clone(),
+                BackgroundOperation::SlideTo(_) => {}
+            },
+            StageCommand::GUIChange { gui_target, sprite_expr, image_mode } => {
+                let Ok(sprite) = sprite_expr.evaluate_into_string() else {
+                    return;
+                };
+                self.gui.retain(|(target, _, _)| target != gui_target);
+                self.gui.push((gui_target.clone(), sprite, image_mode.clone()));
+            },
+            StageCommand::NvlChange { operation } => match operation {
+                NvlOperation::Begin => self.nvl = true,
+                NvlOperation::End => self.nvl = false,
+                NvlOperation::Clear => {}
+            },
+            StageCommand::CharacterChange { character, operation, .. } => {
+                let on_stage = self.characters.iter_mut().find(|c| &c.name == character);
+                match (operation, on_stage) {
+                    (CharacterOperation::Spawn(info), _) => {
+                        self.characters.retain(|c| &c.name != character);
+                        self.characters.push(CharacterSnapshot {
+                            name: character.clone(),
+                            emotion: info.emotion.clone(),
+                            position: info.position.clone(),
+                            direction: None,
+                        });
+                    },
+                    (CharacterOperation::EmotionChange(emotion), Some(snapshot)) => snapshot.emotion = Some(emotion.clone()),
+                    (CharacterOperation::Look(direction), Some(snapshot)) => snapshot.direction = Some(direction.clone()),
+                    (CharacterOperation::Move(position), Some(snapshot)) => snapshot.position = position.clone(),
+                    (CharacterOperation::Despawn(_), Some(_)) => self.characters.retain(|c| &c.name != character),
+                    _ => {}
+                }
+            },
+            StageCommand::Group(commands) => {
+                for command in commands {
+                    self.apply(command);
+                }
+            },
+            StageCommand::SceneChange { .. } | StageCommand::ActChange { .. } => {}
+        }
+    }
+
+    /// Writes the messages recreating this stage, without fading or dissolving
+    pub fn write_messages(
+        self,
+        background_change_message: &mut MessageWriter<BackgroundChangeMessage>,
+        character_change_message: &mut MessageWriter<CharacterChangeMessage>,
+        gui_change_message: &mut MessageWriter<GUIChangeMessage>,
+        nvl_message: &mut MessageWriter<NvlMessage>,
+    ) {
+        if let Some(background) = self.background {
+            background_change_message.write(BackgroundChangeMessage {
+                operation: BackgroundOperation::ChangeTo(background),
+                timing: Timing::default(),
+            });
+        }
+        for character in self.characters {
+            character_change_message.write(CharacterChangeMessage {
+                character: character.name.clone(),
+                operation: CharacterOperation::Spawn(SpawnInfo {
+                    emotion: character.emotion,
+                    position: character.position,
+                    fading: false,
+                }),
+                timing: Timing::default(),
+            });
+            if let Some(direction) = character.direction {
+                character_change_message.write(CharacterChangeMessage {
+                    character: character.name,
+                    operation: CharacterOperation::Look(direction),
+                    timing: Timing::default(),
+                });
+            }
+        }
+        for (gui_target, sprite_id, image_mode) in self.gui {
+            gui_change_message.write(GUIChangeMessage { gui_target, sprite_id, image_mode });
+        }
+        if self.nvl {
+            nvl_message.write(NvlMessage { operation: NvlOperation::Begin });
+        }
+    }
+}
+
+/// Statements run before reaching `statement` of `scene`, following the scene changes from the act entrypoint.
+/// When the scene cannot be reached that way, only the statements preceding it in the same scene are returned.
+pub(crate) fn preceding_statements<'a>(act: &'a Act, scene: &str, statement: usize) -> Vec<&'a Statement> {
+    let mut statements = Vec::new();
+    let mut visited = HashSet::new();
+    let mut current = act.entrypoint.as_str();
+    let mut reached = current == scene;
+
+    while !reached && visited.insert(current) {
+        let Some(current_scene) = act.scenes.get(current) else {
+            break;
+        };
+        let mut next_scene = None;
+        for stm in &current_scene.statements {
+            if let Statement::Stage(StageCommand::SceneChange { scene_expr }) = stm {
+                next_scene = scene_expr.evaluate_into_string().ok();
+                break;
+            }
+            statements.push(stm);
+        }
+        match next_scene.and_then(|next| act.scenes.get_key_value(&next)) {
+            Some((name, _)) => {
+                current = name.as_str();
+                reached = current == scene;
+            },
+            None => break,
+        }
+    }
+    if !reached {
+        warn!("Scene '{}' is not reachable from entrypoint '{}', fast-forwarding its own statements only", scene, act.entrypoint);
+        statements.clear();
+    }
+
+    if let Some(target) = act.scenes.get(scene) {
+        statements.extend(target.statements.iter().take(statement));
+    }
+    statements
+}
@@ -0,0 +1,125 @@
+use std::collections::{HashMap, HashSet};
+use anyhow::{Context, Result};
+use bevy::prelude::*;
+
+use crate::{
+    background::controller::BackgroundOperation,
+    character::CharacterOperation,
+    chat::controller::GuiChangeTarget,
+    compiler::ast::{Act, Evaluate, StageCommand, Statement, TextItem}
+};
+
+/// Assets referenced by an [Act], computed from its AST.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct ActManifest {
+    pub backgrounds: HashSet<String>,
+    /// Character names mapped to the emotions the act uses explicitly
+    pub characters: HashMap<String, HashSet<String>>,
+    pub gui: HashSet<String>,
+    /// Characters with dialogue lines, whose configs hold their textbox and namebox
+    pub speakers: HashSet<String>,
+    /// Acts reachable from this one, in the same chapter
+    pub next_acts: HashSet<String>,
+}
+
+impl ActManifest {
+    pub fn from_act(act: &Act) -> Result<Self> {
+        let mut manifest = ActManifest::default();
+        for scene in act.scenes.values() {
+            for statement in &scene.statements {
+                manifest.add_statement(statement)
+                    .with_context(|| format!("...while building manifest of scene '{}'", scene.name))?;
+            }
+        }
+        Ok(manifest)
+    }
+
+    fn add_statement(&mut self, statement: &Statement) -> Result<()> {
+        match statement {
+            Statement::Stage(stage) => self.add_stage_command(stage),
+            // The player has no config
+            Statement::TextItem(TextItem::Dialogue(dialogue)) if dialogue.character != "[_PLAYERNAME_]" => {
+                self.speakers.insert(dialogue.character.clone());
+                Ok(())
+            },
+            _ => Ok(()),
+        }
+    }
+
+    fn add_stage_command(&mut self, stage: &StageCommand) -> Result<()> {
+        match stage {
+            StageCommand::BackgroundChange { operation, .. } => match operation {
+                BackgroundOperation::ChangeTo(target) | BackgroundOperation::DissolveTo(Some(target)) => {
+                    self.backgrounds.insert(target.clone());
+                },
+                _ => {}
+            },
+            // Themes are loaded when switched to
+            StageCommand::GUIChange { gui_target: GuiChangeTarget::Theme, .. } => {},
+            StageCommand::GUIChange { sprite_expr, .. } => {
+                self.gui.insert(sprite_expr.evaluate_into_string()?);
+            },
+            StageCommand::CharacterChange { character, operation, .. } => {
+                let emotions = self.characters.entry(character.clone()).or_default();
+                match operation {
+                    CharacterOperation::Spawn(info) => {
+                        if let Some(emotion) = &info.emotion {
+                            emotions.insert(emotion.clone());
+                        }
+                    },
+                    CharacterOperation::EmotionChange(emotion) => {
+                        emotions.insert(emotion.clone());
+                    },
+                    _ => {}
+                }
+            },
+            StageCommand::ActChange { act_expr } => {
+                self.next_acts.insert(act_expr.evaluate_into_string()?);
+            },
+            StageCommand::Group(commands) => {
+                for command in commands {
+                    self.add_stage_command(command)?;
+                }
+            },
+            StageCommand::SceneChange { .. } | StageCommand::NvlChange { .. } => {}
+        }
+        Ok(())
+    }
+
+    pub fn extend(&mut self, other: ActManifest) {
+        self.backgrounds.extend(other.backgrounds);
+        self.gui.extend(other.gui);
+        self.speakers.extend(other.speakers);
+        self.next_acts.extend(other.next_acts);
+        for (character, emotions) in other.characters {
+            self.characters.entry(character).or_default().extend(emotions);
+        }
+    }
+}
+
+/* Resources */
+/// Assets needed by the running act, and the ones to preload for the acts following it.
+/// Controllers request the listed assets every time this resource changes.
+#[derive(Resource, Debug, Default)]
+pub(crate) struct ActManifests {
+    pub required: ActManifest,
+    pub preload: ActManifest,
+}
+
+impl ActManifests {
+    pub fn backgrounds(&self) -> impl Iterator<Item = &String> {
+        self.required.backgrounds.iter().chain(self.preload.backgrounds.iter())
+    }
+
+    pub fn gui(&self) -> impl Iterator<Item = &String> {
+        self.required.gui.iter().chain(self.preload.gui.iter())
+    }
+
+    pub fn speakers(&self) -> impl Iterator<Item = &String> {
+        self.required.speakers.iter().chain(self.preload.speakers.iter())
+    }
+
+    pub fn characters(&self) -> impl Iterator<Item = (&String, &HashSet<String>)> {
+        self.required.characters.iter().chain(self.preload.characters.iter())
+    }
+}
@@ -1,5 +1,7 @@
 pub mod controller;
 pub mod ast;
 pub mod calling;
+pub mod manifest;
+pub mod fast_forward;
 
 pub use controller::Compiler;
\ No newline at end of file
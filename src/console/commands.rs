@@ -0,0 +1,82 @@
+use anyhow::{Context, Result, anyhow, bail};
+
+use crate::character::controller::CharacterPosition;
+
+pub(super) const HELP: &str = "\
+jump [act] <scene>                   jump to a scene, of the current act by default
+set <playername|blocking|rewinding> <value>
+spawn <character> [emotion] [at <position>]
+despawn <character>
+bg <background>                      change the background
+pause | resume | stop
+clear | help";
+
+/// Variables of the running [VisualNovelState](crate::VisualNovelState) which can be set from the console
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum ConsoleVariable {
+    PlayerName(String),
+    Blocking(bool),
+    Rewinding(usize),
+}
+
+/// Commands accepted by the developer console
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum ConsoleCommand {
+    Help,
+    Clear,
+    Jump { act: Option<String>, scene: String },
+    Set(ConsoleVariable),
+    Spawn { character: String, emotion: Option<String>, position: CharacterPosition },
+    Despawn(String),
+    Background(String),
+    Pause,
+    Resume,
+    Stop,
+}
+
+impl ConsoleCommand {
+    pub fn parse(line: &str) -> Result<Self> {
+        let mut words = line.split_whitespace();
+        let command = words.next().context("Empty command")?;
+        let args: Vec<&str> = words.collect();
+        let command = match (command, args.as_slice()) {
+            ("help", []) => ConsoleCommand::Help,
+            ("clear", []) => ConsoleCommand::Clear,
+            ("jump", [scene]) => ConsoleCommand::Jump { act: None, scene: scene.to_string() },
+            ("jump", [act, scene]) => ConsoleCommand::Jump { act: Some(act.to_string()), scene: scene.to_string() },
+            ("set", [variable, value @ ..]) if !value.is_empty() => {
+                ConsoleCommand::Set(parse_variable(variable, &value.join(" "))?)
+            },
+            ("spawn", [character, rest @ ..]) => {
+                let (emotion, position) = match rest.iter().position(|w| *w == "at") {
+                    Some(index) => (&rest[..index], rest[index + 1..].join(" ")),
+                    None => (rest, String::from("center")),
+                };
+                let emotion = match emotion {
+                    [] => None,
+                    [emotion] => Some(emotion.to_string()),
+                    _ => bail!("Expected a single emotion, found '{}'", emotion.join(" ")),
+                };
+                let position = CharacterPosition::try_from(position.as_str())
+                    .map_err(|e| anyhow!("{}", e))?;
+                ConsoleCommand::Spawn { character: character.to_string(), emotion, position }
+            },
+            ("despawn", [character]) => ConsoleCommand::Despawn(character.to_string()),
+            ("bg", [background]) => ConsoleCommand::Background(background.to_string()),
+            ("pause", []) => ConsoleCommand::Pause,
+            ("resume", []) => ConsoleCommand::Resume,
+            ("stop", []) => ConsoleCommand::Stop,
+            (command, _) => bail!("Invalid command '{}', type 'help' for the list of commands", command),
+        };
+        Ok(command)
+    }
+}
+
+fn parse_variable(variable: &str, value: &str) -> Result<ConsoleVariable> {
+    match variable {
+        "playername" => Ok(ConsoleVariable::PlayerName(value.to_owned())),
+        "blocking" => Ok(ConsoleVariable::Blocking(value.parse().context("Expected true or false")?)),
+        "rewinding" => Ok(ConsoleVariable::Rewinding(value.parse().context("Expected a positive number")?)),
+        other => bail!("Unknown variable '{}'", other),
+    }
+}
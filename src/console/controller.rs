@@ -0,0 +1,303 @@
+use anyhow::Result;
+use bevy::asset::LoadState;
+use bevy::ecs::system::SystemParam;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::prelude::*;
+
+use crate::{
+    BackgroundChangeMessage, CharacterChangeMessage, SabiJump, SabiPause, SabiResume, SabiSettings, SabiStop, ScriptId, VisualNovelState,
+    background::controller::BackgroundOperation,
+    character::{CharacterConfig, CharacterOperation, controller::{FadingCharacters, MovingCharacters, SpawnInfo}},
+    compiler::{controller::{CurrentScript, SabiRunState, SabiState}, manifest::ActManifests},
+    console::{
+        commands::{ConsoleCommand, ConsoleVariable, HELP},
+        ui::{ConsoleInspector, ConsoleLog, ConsoleOverlay, ConsolePrompt, console_overlay}
+    },
+    tween::Timing
+};
+
+/// Amount of lines kept in the console log
+const LOG_LINES: usize = 12;
+
+/* Resources */
+/// State of the developer console, toggled with [SabiSettings::console_key]
+#[derive(Resource, Default)]
+pub(crate) struct DevConsole {
+    open: bool,
+    input: String,
+    log: Vec<String>,
+    /// Stage commands waiting for their assets, which are only loaded when listed in the [ActManifests]
+    pending: Vec<ConsoleCommand>,
+}
+
+impl DevConsole {
+    fn print(&mut self, line: impl Into<String>) {
+        self.log.extend(line.into().lines().map(String::from));
+        let overflow = self.log.len().saturating_sub(LOG_LINES);
+        self.log.drain(..overflow);
+    }
+}
+
+/* System Params */
+/// Messages the console commands are translated into
+#[derive(SystemParam)]
+struct ConsoleWriters<'w> {
+    jump: MessageWriter<'w, SabiJump>,
+    pause: MessageWriter<'w, SabiPause>,
+    resume: MessageWriter<'w, SabiResume>,
+    stop: MessageWriter<'w, SabiStop>,
+    background: MessageWriter<'w, BackgroundChangeMessage>,
+    character: MessageWriter<'w, CharacterChangeMessage>,
+}
+
+pub(crate) struct ConsoleController;
+impl Plugin for ConsoleController {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DevConsole>()
+            .add_systems(Update, (toggle_console, read_console_input, run_pending_commands, update_inspector).chain());
+    }
+}
+
+/// Run condition blocking player input while the console is being typed into
+pub(crate) fn console_closed(console: Option<Res<DevConsole>>) -> bool {
+    console.is_none_or(|console| !console.open)
+}
+
+fn toggle_console(
+    mut commands: Commands,
+    mut console: ResMut<DevConsole>,
+    keys: Res<ButtonInput<KeyCode>>,
+    overlay: Option<Single<Entity, With<ConsoleOverlay>>>,
+    settings: Res<SabiSettings>,
+) {
+    let Some(key) = settings.console_key else {
+        return;
+    };
+    if !keys.just_pressed(key) {
+        return;
+    }
+    console.open = !console.open;
+    match overlay {
+        Some(overlay) => commands.entity(*overlay).despawn(),
+        None => { commands.spawn(console_overlay()); },
+    }
+}
+
+/// Edits the command line, running it when Enter is pressed
+fn read_console_input(
+    mut console: ResMut<DevConsole>,
+    mut keyboard_messages: MessageReader<KeyboardInput>,
+    mut game_state: ResMut<VisualNovelState>,
+    mut writers: ConsoleWriters,
+    state: Res<State<SabiState>>,
+    current_script: Option<Res<CurrentScript>>,
+    settings: Res<SabiSettings>,
+    mut manifests: Option<ResMut<ActManifests>>,
+) {
+    if !console.open {
+        keyboard_messages.clear();
+        return;
+    }
+    for input in keyboard_messages.read() {
+        if !input.state.is_pressed() || Some(input.key_code) == settings.console_key {
+            continue;
+        }
+        match &input.logical_key {
+            Key::Enter => {
+                let line = std::mem::take(&mut console.input);
+                if line.trim().is_empty() {
+                    continue;
+                }
+                console.print(format!("> {}", line));
+                let script = current_script.as_ref().map(|s| &s.0);
+                let result = ConsoleCommand::parse(&line).and_then(|command| {
+                    run_command(command, &mut console, &mut game_state, &mut writers, manifests.as_deref_mut(), *state.get(), script)
+                });
+                if let Err(e) = result {
+                    console.print(format!("{:#}", e));
+                }
+            },
+            Key::Backspace => { console.input.pop(); },
+            _ => {
+                if let Some(text) = &input.text {
+                    console.input.extend(text.chars().filter(|c| !c.is_control()));
+                }
+            }
+        }
+    }
+}
+
+fn run_command(
+    command: ConsoleCommand,
+    console: &mut DevConsole,
+    game_state: &mut VisualNovelState,
+    writers: &mut ConsoleWriters,
+    manifests: Option<&mut ActManifests>,
+    state: SabiState,
+    script: Option<&ScriptId>,
+) -> Result<()> {
+    let requires_run = !matches!(command, ConsoleCommand::Help | ConsoleCommand::Clear);
+    if requires_run && state != SabiState::Running {
+        anyhow::bail!("No script is running");
+    }
+    match command {
+        ConsoleCommand::Help => console.print(HELP),
+        ConsoleCommand::Clear => console.log.clear(),
+        ConsoleCommand::Jump { act, scene } => {
+            let current = script.ok_or(anyhow::anyhow!("No script is running"))?;
+            let script = ScriptId {
+                chapter: current.chapter.clone(),
+                act: act.unwrap_or(current.act.clone()),
+            };
+            writers.jump.write(SabiJump { script, scene: Some(scene) });
+        },
+        ConsoleCommand::Set(variable) => match variable {
+            ConsoleVariable::PlayerName(name) => game_state.playername = name,
+            ConsoleVariable::Blocking(blocking) => game_state.blocking = blocking,
+            ConsoleVariable::Rewinding(rewinding) => game_state.rewinding = rewinding,
+        },
+        ConsoleCommand::Spawn { ref character, ref emotion, .. } => {
+            let manifests = manifests.ok_or(anyhow::anyhow!("No act manifest to load '{}' from", character))?;
+            let emotions = manifests.required.characters.entry(character.clone()).or_default();
+            emotions.extend(emotion.clone());
+            console.pending.push(command);
+        },
+        ConsoleCommand::Despawn(character) => {
+            writers.character.write(CharacterChangeMessage {
+                character,
+                operation: CharacterOperation::Despawn(false),
+                timing: Timing::default(),
+            });
+        },
+        ConsoleCommand::Background(ref background) => {
+            let manifests = manifests.ok_or(anyhow::anyhow!("No act manifest to load '{}' from", background))?;
+            manifests.required.backgrounds.insert(background.clone());
+            console.pending.push(command);
+        },
+        ConsoleCommand::Pause => { writers.pause.write(SabiPause); },
+        ConsoleCommand::Resume => { writers.resume.write(SabiResume); },
+        ConsoleCommand::Stop => { writers.stop.write(SabiStop); },
+    }
+    Ok(())
+}
+
+/// Writes the stage commands of the console once the controllers have loaded their assets,
+/// asking the [AssetServer] for the same paths so that the handles are shared with them
+fn run_pending_commands(
+    mut console: ResMut<DevConsole>,
+    mut writers: ConsoleWriters,
+    asset_server: Res<AssetServer>,
+    config_assets: Res<Assets<CharacterConfig>>,
+    settings: Res<SabiSettings>,
+    state: Res<State<SabiState>>,
+) {
+    if *state.get() != SabiState::Running {
+        console.pending.clear();
+        return;
+    }
+    let pending = std::mem::take(&mut console.pending);
+    for command in pending {
+        let ready = match &command {
+            ConsoleCommand::Background(background) => {
+                let handle: Handle<Image> = asset_server.load(format!("{}/{}.{}", settings.backgrounds_path, background, settings.image_extension));
+                asset_ready(&asset_server, &handle, background)
+            },
+            ConsoleCommand::Spawn { character, emotion, .. } => {
+                let config: Handle<CharacterConfig> = asset_server.load(format!("{}/{}/character.json", settings.characters_path, character));
+                match (asset_ready(&asset_server, &config, character), config_assets.get(&config)) {
+                    (Ok(true), Some(config)) => {
+                        let emotion = emotion.as_ref().unwrap_or(&config.emotion);
+                        let sprite: Handle<Image> = asset_server.load(format!(
+                            "{}/{}/{}/{}.{}", settings.characters_path, character, config.outfit, emotion, settings.image_extension
+                        ));
+                        asset_ready(&asset_server, &sprite, &format!("{} ({})", character, emotion))
+                    },
+                    (Ok(_), _) => Ok(false),
+                    (Err(e), _) => Err(e),
+                }
+            },
+            _ => Ok(true),
+        };
+        match ready {
+            Ok(true) => write_stage_command(command, &mut writers),
+            Ok(false) => console.pending.push(command),
+            Err(e) => console.print(format!("{:#}", e)),
+        }
+    }
+}
+/// Whether the asset finished loading, failing when it cannot be
+fn asset_ready<A: Asset>(asset_server: &AssetServer, handle: &Handle<A>, name: &str) -> Result<bool> {
+    match asset_server.load_state(handle) {
+        LoadState::Loaded => Ok(true),
+        LoadState::Failed(e) => Err(anyhow::anyhow!("Could not load '{}': {}", name, e)),
+        _ => Ok(false),
+    }
+}
+fn write_stage_command(command: ConsoleCommand, writers: &mut ConsoleWriters) {
+    match command {
+        ConsoleCommand::Spawn { character, emotion, position } => {
+            writers.character.write(CharacterChangeMessage {
+                character,
+                operation: CharacterOperation::Spawn(SpawnInfo { emotion, position, fading: false }),
+                timing: Timing::default(),
+            });
+        },
+        ConsoleCommand::Background(background) => {
+            writers.background.write(BackgroundChangeMessage {
+                operation: BackgroundOperation::ChangeTo(background),
+                timing: Timing::default(),
+            });
+        },
+        _ => {}
+    }
+}
+/// Refreshes the runtime state shown by the console
+fn update_inspector(
+    console: Res<DevConsole>,
+    game_state: Res<VisualNovelState>,
+    state: Res<State<SabiState>>,
+    run_state: Option<Res<State<SabiRunState>>>,
+    current_script: Option<Res<CurrentScript>>,
+    fading_characters: Res<FadingCharacters>,
+    moving_characters: Res<MovingCharacters>,
+    q_characters: Query<&CharacterConfig>,
+    inspector: Option<Single<&mut Text, (With<ConsoleInspector>, Without<ConsoleLog>, Without<ConsolePrompt>)>>,
+    log: Option<Single<&mut Text, (With<ConsoleLog>, Without<ConsolePrompt>)>>,
+    prompt: Option<Single<&mut Text, With<ConsolePrompt>>>,
+) {
+    let (Some(mut inspector), Some(mut log), Some(mut prompt)) = (inspector, log, prompt) else {
+        return;
+    };
+    let character_name = |entity: Entity| q_characters.get(entity)
+        .map(|c| c.name.clone())
+        .unwrap_or(format!("{:?}", entity));
+
+    let script = match &current_script {
+        Some(script) if *state.get() != SabiState::Idle => format!("{}/{}", script.0.chapter, script.0.act),
+        _ => String::from("-"),
+    };
+    let run_state = run_state.map(|s| format!(" ({:?})", s.get())).unwrap_or_default();
+    let statement = game_state.statements.position()
+        .map(|p| p.to_string())
+        .unwrap_or(String::from("-"));
+    let fading: Vec<String> = fading_characters.0.iter()
+        .map(|f| format!(
+            "{} ({} -> {}, {:.0}%{})",
+            character_name(f.entity), f.from, f.to, f.tween.progress() * 100., if f.despawn { ", despawning" } else { "" }
+        ))
+        .collect();
+    let moving: Vec<String> = moving_characters.0.iter()
+        .map(|m| format!("{} -> {}% ({:.0}%)", character_name(m.entity), m.to, m.tween.progress() * 100.))
+        .collect();
+
+    inspector.0 = format!(
+        "Script: {}   State: {:?}{}\nScene: {}   Statement: {}/{}\nBlocking: {}   Rewinding: {}\nFading: {}\nMoving: {}",
+        script, state.get(), run_state,
+        game_state.scene.name, statement, game_state.scene.statements.len(),
+        game_state.blocking, game_state.rewinding,
+        if fading.is_empty() { String::from("-") } else { fading.join(", ") },
+        if moving.is_empty() { String::from("-") } else { moving.join(", ") },
+    );
+    log.0 = console.log.join("\n");
+    prompt.0 = format!("> {}_", console.input);
+}
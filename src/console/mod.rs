@@ -0,0 +1,6 @@
+mod commands;
+pub(crate) mod controller;
+mod ui;
+
+pub(crate) use controller::ConsoleController;
+pub(crate) use controller::console_closed;
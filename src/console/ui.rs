@@ -0,0 +1,52 @@
+use bevy::{color::palettes::css::{BLACK, LIME, WHITE, YELLOW}, prelude::*};
+
+/* Components */
+#[derive(Component)]
+pub(crate) struct ConsoleOverlay;
+#[derive(Component)]
+pub(crate) struct ConsoleInspector;
+#[derive(Component)]
+pub(crate) struct ConsoleLog;
+#[derive(Component)]
+pub(crate) struct ConsolePrompt;
+
+pub(in crate::console) fn console_overlay() -> impl Bundle {
+    (
+        Node {
+            position_type: PositionType::Absolute,
+            top: px(0.),
+            left: px(0.),
+            width: percent(100.),
+            height: percent(45.),
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::SpaceBetween,
+            padding: UiRect::all(px(10.)),
+            overflow: Overflow::clip(),
+            ..default()
+        },
+        BackgroundColor(Color::Srgba(BLACK).with_alpha(0.85)),
+        // Right below the error overlay
+        GlobalZIndex(i32::MAX - 1),
+        ConsoleOverlay,
+        children![
+            (
+                Text::default(),
+                TextFont { font_size: 14., ..default() },
+                TextColor(Color::Srgba(YELLOW)),
+                ConsoleInspector,
+            ),
+            (
+                Text::default(),
+                TextFont { font_size: 14., ..default() },
+                TextColor(Color::Srgba(WHITE)),
+                ConsoleLog,
+            ),
+            (
+                Text::new("> "),
+                TextFont { font_size: 16., ..default() },
+                TextColor(Color::Srgba(LIME)),
+                ConsolePrompt,
+            ),
+        ]
+    )
+}
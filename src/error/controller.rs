@@ -0,0 +1,216 @@
+use bevy::prelude::*;
+use bevy_ui_widgets::Activate;
+
+use crate::{
+    Cursor, HistoryItem, SabiSettings, SabiStop, VisualNovelState,
+    compiler::controller::{SabiRunState, SabiState},
+    error::ui::{ErrorOverlay, ErrorOverlayText, error_overlay}
+};
+
+/// Amount of distinct errors listed on the development overlay, the following ones being only logged
+const MAX_OVERLAY_ERRORS: usize = 10;
+
+/* Resources */
+/// Errors of the sabi systems, waiting to be handled according to the [ErrorPolicy]
+#[derive(Resource, Default)]
+pub(crate) struct SabiErrors {
+    pending: Vec<String>,
+    /// Distinct errors listed on the development overlay
+    shown: Vec<String>,
+}
+
+impl SabiErrors {
+    pub fn report(&mut self, message: impl Into<String>) {
+        self.pending.push(message.into());
+    }
+}
+
+/* Custom types */
+/// Behaviour of sabi when one of its systems returns an error.
+/// Only the sabi systems are concerned, the error handler of the app being left to the game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// Panic on the first error
+    Panic,
+    /// Pause the run and show an overlay with the error, letting the developer skip the statement or restart the scene
+    Development,
+    /// Log the error, skip the failing statement and write a [SabiError] message
+    Release,
+}
+
+impl Default for ErrorPolicy {
+    fn default() -> Self {
+        if cfg!(debug_assertions) { ErrorPolicy::Development } else { ErrorPolicy::Release }
+    }
+}
+
+/// Position in the scripts at the moment of an error.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptLocation {
+    pub act: String,
+    pub scene: String,
+    pub statement: Option<usize>,
+}
+
+impl std::fmt::Display for ScriptLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.statement {
+            Some(index) => write!(f, "act '{}', scene '{}', statement {}", self.act, self.scene, index),
+            None => write!(f, "act '{}', scene '{}'", self.act, self.scene),
+        }
+    }
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ErrorOverlayButton {
+    SkipStatement,
+    RestartScene,
+    Stop,
+}
+
+/* Messages */
+/// Written for every error handled by the [ErrorPolicy::Development] and [ErrorPolicy::Release] policies.
+#[derive(Message, Debug, Clone)]
+pub struct SabiError {
+    pub message: String,
+    pub location: Option<ScriptLocation>,
+}
+
+pub(crate) struct ErrorController;
+impl Plugin for ErrorController {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SabiErrors>()
+            .add_message::<SabiError>()
+            .add_systems(Update, handle_errors)
+            .add_systems(OnEnter(SabiState::Idle), despawn_overlay)
+            .add_observer(overlay_button_clicked);
+    }
+}
+
+/// Queues the error returned by a sabi system, which is piped into it, to be handled according to the [ErrorPolicy]
+pub(crate) fn report_errors(In(result): In<Result<(), BevyError>>, mut errors: ResMut<SabiErrors>) {
+    if let Err(e) = result {
+        errors.report(e.to_string());
+    }
+}
+
+fn script_location(game_state: &VisualNovelState) -> Option<ScriptLocation> {
+    if game_state.act.name.is_empty() {
+        return None;
+    }
+    Some(ScriptLocation {
+        act: game_state.act.name.clone(),
+        scene: game_state.scene.name.clone(),
+        statement: game_state.statements.position(),
+    })
+}
+
+/// Drains the errors reported to [SabiErrors] and applies the configured [ErrorPolicy]
+fn handle_errors(
+    mut commands: Commands,
+    mut sabi_errors: ResMut<SabiErrors>,
+    mut game_state: ResMut<VisualNovelState>,
+    mut error_writer: MessageWriter<SabiError>,
+    mut run_state: ResMut<NextState<SabiRunState>>,
+    mut sabi_state: ResMut<NextState<SabiState>>,
+    current_state: Res<State<SabiState>>,
+    overlay_text: Option<Single<&mut Text, With<ErrorOverlayText>>>,
+    settings: Res<SabiSettings>,
+) {
+    let mut errors = std::mem::take(&mut sabi_errors.pending);
+    // A failing system reports the same error on every frame
+    errors.dedup();
+    if errors.is_empty() {
+        return;
+    }
+
+    let location = script_location(&game_state);
+    let location_text = location.as_ref().map(|l| l.to_string()).unwrap_or(String::from("outside of a script"));
+    match settings.error_policy {
+        ErrorPolicy::Panic => {
+            panic!("Sabi error at {}:\n{}", location_text, errors.join("\n"));
+        },
+        ErrorPolicy::Development => {
+            let statement = game_state.statements.current()
+                .map(|s| format!("\n{:?}", s))
+                .unwrap_or_default();
+            for error in &errors {
+                if sabi_errors.shown.len() < MAX_OVERLAY_ERRORS && !sabi_errors.shown.contains(error) {
+                    sabi_errors.shown.push(error.clone());
+                }
+            }
+            let report = format!("At {}{}\n\n{}", location_text, statement, sabi_errors.shown.join("\n"));
+            if let Some(mut text) = overlay_text {
+                text.0 = report;
+            } else {
+                commands.spawn(error_overlay(report, *current_state.get() == SabiState::Running));
+            }
+            if *current_state.get() == SabiState::Running {
+                run_state.set(SabiRunState::Paused);
+            }
+        },
+        ErrorPolicy::Release => {
+            for error in &errors {
+                error!("Sabi error at {}: {}", location_text, error);
+            }
+            match current_state.get() {
+                SabiState::Running => { game_state.blocking = false; },
+                // The run cannot start without its assets
+                SabiState::WaitingForControllers => { sabi_state.set(SabiState::Idle); },
+                SabiState::Idle => {},
+            }
+        },
+    }
+    for message in errors {
+        error_writer.write(SabiError { message, location: location.clone() });
+    }
+}
+
+fn overlay_button_clicked(
+    trigger: On<Activate>,
+    mut commands: Commands,
+    q_buttons: Query<&ErrorOverlayButton>,
+    overlay: Option<Single<Entity, With<ErrorOverlay>>>,
+    mut sabi_errors: ResMut<SabiErrors>,
+    mut game_state: ResMut<VisualNovelState>,
+    mut run_state: ResMut<NextState<SabiRunState>>,
+    mut stop_writer: MessageWriter<SabiStop>,
+) {
+    let Ok(button) = q_buttons.get(trigger.entity) else {
+        return;
+    };
+    match button {
+        ErrorOverlayButton::SkipStatement => {
+            info!("[ Skipping failed statement ]");
+            game_state.blocking = false;
+            run_state.set(SabiRunState::Playing);
+        },
+        ErrorOverlayButton::RestartScene => {
+            info!("[ Restarting scene '{}' ]", game_state.scene.name);
+            game_state.statements = Cursor::new(game_state.scene.statements.clone());
+            let descriptor = HistoryItem::Scene(game_state.scene.name.clone());
+            game_state.history.push(descriptor);
+            game_state.rewinding = 0;
+            game_state.blocking = false;
+            run_state.set(SabiRunState::Playing);
+        },
+        ErrorOverlayButton::Stop => {
+            stop_writer.write(SabiStop);
+        },
+    }
+    sabi_errors.shown.clear();
+    if let Some(overlay) = overlay {
+        commands.entity(*overlay).despawn();
+    }
+}
+
+fn despawn_overlay(
+    mut commands: Commands,
+    overlay: Option<Single<Entity, With<ErrorOverlay>>>,
+    mut sabi_errors: ResMut<SabiErrors>,
+) {
+    sabi_errors.shown.clear();
+    if let Some(overlay) = overlay {
+        commands.entity(*overlay).despawn();
+    }
+}
@@ -0,0 +1,9 @@
+pub(crate) mod controller;
+mod ui;
+
+pub(crate) use controller::ErrorController;
+pub(crate) use controller::SabiErrors;
+pub(crate) use controller::report_errors;
+pub use controller::ErrorPolicy;
+pub use controller::SabiError;
+pub use controller::ScriptLocation;
@@ -0,0 +1,89 @@
+use bevy::{color::palettes::css::{BLACK, RED, WHITE}, prelude::*};
+use bevy_ui_widgets::Button;
+
+use crate::error::controller::ErrorOverlayButton;
+
+/* Components */
+#[derive(Component)]
+pub(crate) struct ErrorOverlay;
+#[derive(Component)]
+pub(crate) struct ErrorOverlayText;
+
+pub(in crate::error) fn error_overlay(report: String, running: bool) -> impl Bundle {
+    let buttons = if running {
+        vec![ErrorOverlayButton::SkipStatement, ErrorOverlayButton::RestartScene, ErrorOverlayButton::Stop]
+    } else {
+        vec![ErrorOverlayButton::Stop]
+    };
+    (
+        Node {
+            position_type: PositionType::Absolute,
+            width: percent(100.),
+            height: percent(100.),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        BackgroundColor(Color::Srgba(BLACK).with_alpha(0.7)),
+        GlobalZIndex(i32::MAX),
+        ErrorOverlay,
+        children![(
+            Node {
+                width: percent(70.),
+                max_height: percent(80.),
+                flex_direction: FlexDirection::Column,
+                row_gap: px(12.),
+                padding: UiRect::all(px(20.)),
+                border: UiRect::all(px(2.)),
+                overflow: Overflow::clip(),
+                ..default()
+            },
+            BorderColor::all(Color::Srgba(RED)),
+            BackgroundColor(Color::Srgba(BLACK)),
+            children![
+                (
+                    Text::new("Sabi error"),
+                    TextFont { font_size: 24., ..default() },
+                    TextColor(Color::Srgba(RED)),
+                ),
+                (
+                    Text::new(report),
+                    TextFont { font_size: 14., ..default() },
+                    TextColor(Color::Srgba(WHITE)),
+                    ErrorOverlayText,
+                ),
+                (
+                    Node {
+                        flex_direction: FlexDirection::Row,
+                        column_gap: px(10.),
+                        ..default()
+                    },
+                    Children::spawn(SpawnIter(buttons.into_iter().map(overlay_button))),
+                )
+            ]
+        )]
+    )
+}
+
+fn overlay_button(action: ErrorOverlayButton) -> impl Bundle {
+    let button_text = match action {
+        ErrorOverlayButton::SkipStatement => "Skip statement",
+        ErrorOverlayButton::RestartScene  => "Restart scene",
+        ErrorOverlayButton::Stop          => "Stop",
+    };
+    (
+        Node {
+            border: UiRect::all(px(2)),
+            padding: UiRect { left: px(5), right: px(5), top: px(3), bottom: px(3) },
+            ..default()
+        },
+        BorderColor::all(Color::WHITE),
+        BorderRadius::MAX,
+        BackgroundColor(Color::Srgba(BLACK)),
+        action,
+        Button,
+        children![
+            Text::new(button_text),
+        ]
+    )
+}
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use bevy::input::mouse::AccumulatedMouseScroll;
+use bevy::picking::hover::HoverMap;
+use bevy::prelude::*;
+
+use crate::chat::controller::{UiButtons, prompt_closed};
+use crate::preferences::{SettingsMenu, settings_closed};
+use crate::compiler::controller::SabiRunState;
+use crate::console::console_closed;
+
+/* Custom types */
+/// High level actions the player can perform during a visual novel run.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum SabiAction {
+    /// Complete the current text scrolling or go to the next statement
+    Advance,
+    /// Go back to the previous dialogue line
+    Rewind,
+    /// Open or close the history panel
+    History,
+    /// Fast-forward through dialogue while the binding is held
+    Skip,
+    /// Toggle the visibility of the dialogue UI
+    HideUi,
+    /// Open or close the settings menu. Closes the history panel if it is open
+    Menu,
+}
+
+/// Physical input which can be bound to a [SabiAction].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputBinding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+    WheelUp,
+    WheelDown,
+}
+
+/* Resources */
+/// Resource mapping every [SabiAction] to the inputs triggering it.
+/// Insert or modify it to remap the default bindings.
+#[derive(Resource, Debug, Clone)]
+pub struct InputBindings(pub HashMap<SabiAction, Vec<InputBinding>>);
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        Self(HashMap::from([
+            (SabiAction::Advance, vec![
+                InputBinding::Key(KeyCode::Space),
+                InputBinding::Key(KeyCode::Enter),
+                InputBinding::Mouse(MouseButton::Left),
+                InputBinding::Gamepad(GamepadButton::South),
+                InputBinding::WheelDown,
+            ]),
+            (SabiAction::Rewind, vec![
+                InputBinding::WheelUp,
+            ]),
+            (SabiAction::History, vec![
+                InputBinding::Key(KeyCode::PageUp),
+            ]),
+            (SabiAction::Skip, vec![
+                InputBinding::Key(KeyCode::ControlLeft),
+                InputBinding::Key(KeyCode::ControlRight),
+            ]),
+            (SabiAction::HideUi, vec![
+                InputBinding::Key(KeyCode::KeyH),
+            ]),
+            (SabiAction::Menu, vec![
+                InputBinding::Key(KeyCode::Escape),
+                InputBinding::Gamepad(GamepadButton::Start),
+            ]),
+        ]))
+    }
+}
+
+impl InputBindings {
+    /// Add a binding to the given action, keeping the existing ones.
+    pub fn bind(&mut self, action: SabiAction, binding: InputBinding) -> &mut Self {
+        let bindings = self.0.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+        self
+    }
+
+    /// Replace every binding of the given action.
+    pub fn set(&mut self, action: SabiAction, bindings: Vec<InputBinding>) -> &mut Self {
+        self.0.insert(action, bindings);
+        self
+    }
+
+    /// Remove a single binding from the given action.
+    pub fn unbind(&mut self, action: SabiAction, binding: InputBinding) -> &mut Self {
+        if let Some(bindings) = self.0.get_mut(&action) {
+            bindings.retain(|b| *b != binding);
+        }
+        self
+    }
+}
+
+/* Messages */
+/// Message written every time the player triggers a [SabiAction].
+/// [SabiAction::Skip] is written on every frame its binding is held.
+#[derive(Message, Debug, Clone, Copy)]
+pub struct SabiActionMessage(pub SabiAction);
+
+pub(crate) struct InputController;
+impl Plugin for InputController {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputBindings>()
+            .add_message::<SabiActionMessage>()
+            .add_systems(Update, read_actions
+                .run_if(in_state(SabiRunState::Playing).or(not(settings_closed)).and(console_closed).and(prompt_closed)));
+    }
+}
+
+/// Translates raw keyboard, mouse and gamepad input into [SabiActionMessage]s according to [InputBindings]
+fn read_actions(
+    bindings: Res<InputBindings>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    scroll: Res<AccumulatedMouseScroll>,
+    gamepads: Query<&Gamepad>,
+    hover_map: Res<HoverMap>,
+    q_buttons: Query<(), With<UiButtons>>,
+    q_settings_menu: Query<(), With<SettingsMenu>>,
+    mut msg_writer: MessageWriter<SabiActionMessage>,
+) {
+    // Clicks over sabi buttons are already handled by their own observers
+    let hovering_button = hover_map.values()
+        .flat_map(|hits| hits.keys())
+        .any(|entity| q_buttons.contains(*entity));
+
+    for (action, action_bindings) in &bindings.0 {
+        // Only the menu binding is read while the settings menu is open, to close it
+        if !q_settings_menu.is_empty() && *action != SabiAction::Menu {
+            continue;
+        }
+        let held = *action == SabiAction::Skip;
+        let triggered = action_bindings.iter().any(|binding| match binding {
+            InputBinding::Key(key) => if held { keys.pressed(*key) } else { keys.just_pressed(*key) },
+            InputBinding::Mouse(button) => {
+                !hovering_button && if held { mouse.pressed(*button) } else { mouse.just_pressed(*button) }
+            },
+            InputBinding::Gamepad(button) => gamepads.iter().any(|gamepad| {
+                if held { gamepad.pressed(*button) } else { gamepad.just_pressed(*button) }
+            }),
+            InputBinding::WheelUp => scroll.delta.y > 0.,
+            InputBinding::WheelDown => scroll.delta.y < 0.,
+        });
+        if triggered {
+            msg_writer.write(SabiActionMessage(*action));
+        }
+    }
+}
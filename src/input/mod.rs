@@ -0,0 +1,7 @@
+pub(crate) mod controller;
+
+pub(crate) use controller::InputController;
+pub use controller::InputBinding;
+pub use controller::InputBindings;
+pub use controller::SabiAction;
+pub use controller::SabiActionMessage;
@@ -1,21 +1,44 @@
+use std::collections::HashMap;
+
 mod background;
 mod character;
 mod chat;
 mod compiler;
+mod console;
+mod error;
+mod input;
 mod loader;
+mod menus;
+mod preferences;
+mod settings;
+mod tween;
 
 use crate::background::*;
 use crate::character::*;
 use crate::chat::*;
+use crate::chat::markup::Markup;
 use crate::compiler::ast::Evaluate;
 use crate::compiler::ast::Statement;
 use crate::compiler::ast::TextItem;
 use crate::compiler::*;
+use crate::console::ConsoleController;
+use crate::error::ErrorController;
+use crate::input::InputController;
 use crate::loader::CharacterJsonLoader;
 use crate::loader::PestLoader;
+use crate::loader::UiThemeLoader;
+use crate::preferences::PreferencesController;
+use crate::chat::theme::UiTheme;
 
 use bevy::prelude::*;
-use bevy::ecs::error::ErrorContext;
+
+pub use crate::compiler::controller::{SabiRunState, SabiRuntime, SabiState};
+pub use crate::error::{ErrorPolicy, SabiError, ScriptLocation};
+pub use crate::chat::{QuickMenu, QuickMenuButton, QuickMenuSelected};
+pub use crate::menus::{SabiMenusPlugin, TitleMenuEntry, TitleMenuSelected};
+pub use crate::preferences::{UserPreferences, WindowModePreference};
+pub use crate::input::{InputBinding, InputBindings, SabiAction, SabiActionMessage};
+pub use crate::settings::{AnimationDurations, MarkupFonts, SabiLayers, SabiSettings, TextSpeeds};
 
 pub(crate) trait VariantKind {
     fn kind(&self) -> usize;
@@ -51,6 +74,15 @@ impl<T> Cursor<T> {
         }
     }
     
+    /// Creates a cursor pointing at the given index, as if it had already been advanced up to it
+    pub(crate) fn with_position(vec: Vec<T>, pos: i32) -> Self {
+        let pos = pos.clamp(-1, vec.len() as i32 - 1);
+        Self {
+            data: vec,
+            pos,
+        }
+    }
+
     pub(crate) fn next(&mut self) -> Option<T>
     where
         T: Clone
@@ -68,6 +100,19 @@ impl<T> Cursor<T> {
         self.data.get(self.pos as usize).cloned()
     }
 
+    /// Index of the current element, if the cursor has been advanced at least once
+    pub(crate) fn position(&self) -> Option<usize> {
+        usize::try_from(self.pos).ok()
+    }
+
+    pub(crate) fn current(&self) -> Option<&T> {
+        self.position().and_then(|pos| self.data.get(pos))
+    }
+
+    pub(crate) fn get(&self, index: usize) -> Option<&T> {
+        self.data.get(index)
+    }
+
     pub(crate) fn find_previous(&self) -> Option<T>
     where
         T: Clone + VariantKind
@@ -103,53 +148,230 @@ pub(crate) struct VisualNovelState {
     blocking: bool,
     pub rewinding: usize,
     pub history: Vec<HistoryItem>,
+    /// Texts entered by the player with `input` statements
+    pub variables: HashMap<String, String>,
 }
 
+/// Position of a statement in the scripts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct StatementId {
+    pub act: String,
+    pub scene: String,
+    /// Index of the statement in the scene
+    pub index: usize,
+}
+
+/// Line shown to the player, as it was displayed
+#[derive(Debug, Clone)]
+pub(crate) struct HistoryLine {
+    /// Name of the speaker, [None] for infotexts
+    pub speaker: Option<String>,
+    /// Text without its markup tags
+    pub text: String,
+}
+
+/// Text entered by the player in an `input` prompt
+#[derive(Debug, Clone)]
+pub(crate) struct EnteredText {
+    /// `playername` or the name of the variable
+    pub target: String,
+    pub value: String,
+}
+
+/// Entry of the history, recorded as the run goes on
+#[derive(Debug, Clone)]
 pub(crate) enum HistoryItem {
-    Statement(ast::Statement),
-    Descriptor(String),
+    /// Statement run in a scene, with the line it showed, the text entered in its prompt
+    /// and the option picked by the player while it was shown, if any
+    Statement { id: StatementId, line: Option<HistoryLine>, input: Option<EnteredText>, choice: Option<String> },
+    Act(String),
+    Scene(String),
+}
+
+/// Format of the transcripts written by [SabiExportLog]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Markdown,
+}
+
+impl LogFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            LogFormat::Text => "txt",
+            LogFormat::Markdown => "md",
+        }
+    }
 }
 
 impl VisualNovelState {
-    pub fn set_rewind(&mut self) {
-        let search_slice = &self.history[..self.history.len() - 1];
-        let last_d = search_slice.iter().rposition(|s| {
-            if let HistoryItem::Statement(stm) = s {
-                matches!(stm, Statement::TextItem(TextItem::Dialogue(_)))
-            } else {
-                false
+    /// Replaces `{playername}` and `{variable}` in a text with their values, keeping unknown names as they are
+    pub fn interpolate(&self, text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find('{') {
+            result.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+            let value = after.find('}').and_then(|end| {
+                let name = &after[..end];
+                let value = if name == "playername" { Some(&self.playername) } else { self.variables.get(name) };
+                value.map(|value| (value, end))
+            });
+            match value {
+                Some((value, end)) => {
+                    result.push_str(value);
+                    rest = &after[end + 1..];
+                },
+                None => {
+                    result.push('{');
+                    rest = after;
+                }
             }
+        }
+        result.push_str(rest);
+        result
+    }
+
+    /// Adds the statement the cursor is on to the history, along with the line it shows
+    pub(crate) fn record(&mut self, statement: &Statement) -> Result<()> {
+        let line = match statement {
+            Statement::TextItem(TextItem::Dialogue(d)) => {
+                let speaker = if d.character == "[_PLAYERNAME_]" { self.playername.clone() } else { d.character.clone() };
+                let text = self.interpolate(&d.dialogue.evaluate_into_string()?);
+                Some(HistoryLine { speaker: Some(speaker), text: Markup::parse(&text).text })
+            },
+            Statement::TextItem(TextItem::InfoText(i)) => {
+                let text = self.interpolate(&i.infotext.evaluate_into_string()?);
+                Some(HistoryLine { speaker: None, text: Markup::parse(&text).text })
+            },
+            _ => None,
+        };
+        let id = StatementId {
+            act: self.act.name.clone(),
+            scene: self.scene.name.clone(),
+            index: self.statements.position().unwrap_or_default(),
+        };
+        self.history.push(HistoryItem::Statement { id, line, input: None, choice: None });
+        Ok(())
+    }
+
+    /// Attaches the text entered by the player to the last recorded statement, the `input` prompting for it
+    pub(crate) fn record_input(&mut self, target: &str, value: &str) {
+        let last_statement = self.history.iter_mut().rev().find_map(|item| match item {
+            HistoryItem::Statement { input, .. } => Some(input),
+            _ => None,
+        });
+        if let Some(input) = last_statement {
+            *input = Some(EnteredText { target: target.to_owned(), value: value.to_owned() });
+        }
+    }
+
+    /// Attaches the option picked by the player to the last recorded statement, the line asking the question.
+    /// Kept on the statement rather than as an entry of its own, so that rewinding counts the same steps.
+    pub(crate) fn record_choice(&mut self, text: &str) {
+        let last_statement = self.history.iter_mut().rev().find_map(|item| match item {
+            HistoryItem::Statement { choice, .. } => Some(choice),
+            _ => None,
         });
-        if let Some(index) = last_d {
+        if let Some(choice) = last_statement {
+            *choice = Some(text.to_owned());
+        }
+    }
+
+    /// Index in the history of the dialogue preceding the last statement
+    fn previous_dialogue(&self) -> Option<usize> {
+        let search_slice = &self.history[..self.history.len().saturating_sub(1)];
+        search_slice.iter().rposition(|s| {
+            matches!(s, HistoryItem::Statement { line: Some(HistoryLine { speaker: Some(_), .. }), .. })
+        })
+    }
+
+    /// Whether [Self::set_rewind] has a dialogue to go back to
+    pub fn can_rewind(&self) -> bool {
+        self.previous_dialogue().is_some()
+    }
+
+    pub fn set_rewind(&mut self) {
+        if let Some(index) = self.previous_dialogue() {
             self.rewinding = self.history.len() - (index + 1);
             self.blocking = false;
         }
     }
 
-    pub fn history_summary(&self) -> Result<Vec<String>> {
-        let mut text: Vec<String> = Vec::new();
-
-        for statement in &self.history {
-            match statement {
-                HistoryItem::Statement(s) => {
-                    if let Statement::TextItem(t) = s {
-                        match t {
-                            TextItem::Dialogue(d) => {
-                                text.push(d.character.clone() + format!(": {}\n", d.dialogue.evaluate_into_string()?).as_str());
-                            },
-                            TextItem::InfoText(i) => {
-                                text.push(i.infotext.evaluate_into_string()? + "\n");
-                            }
-                        }
-                    }
-                }
-                HistoryItem::Descriptor(s) => {
-                    text.push(s.clone() + "\n");
-                }
+    /// Whether the line at `index` of the history can be gone back to,
+    /// being earlier in the current scene with no code statement after it
+    pub(crate) fn can_rewind_to(&self, index: usize) -> bool {
+        let Some(HistoryItem::Statement { id, line: Some(_), .. }) = self.history.get(index) else {
+            return false;
+        };
+        let Some(current) = self.statements.position() else {
+            return false;
+        };
+        let steps = self.history.len() - (index + 1);
+        steps > 0
+            && id.act == self.act.name
+            && id.scene == self.scene.name
+            && current.checked_sub(id.index) == Some(steps)
+            && self.history[index + 1..].iter().all(|item| matches!(item, HistoryItem::Statement { .. }))
+            && (id.index..=current).all(|i| !matches!(self.statements.get(i), Some(Statement::Code(_))))
+    }
+
+    /// Log of the playthrough: acts and scenes entered, lines shown, texts entered and options picked by the player
+    pub(crate) fn transcript(&self, format: LogFormat) -> String {
+        let mut transcript = String::new();
+        for item in &self.history {
+            let (line, input, choice) = match (item, format) {
+                (HistoryItem::Statement { line, input, choice, .. }, _) => (line, input, choice),
+                (HistoryItem::Act(name), LogFormat::Text) => {
+                    transcript.push_str(&format!("\n=== Act: {} ===\n", name));
+                    continue;
+                },
+                (HistoryItem::Act(name), LogFormat::Markdown) => {
+                    transcript.push_str(&format!("## Act: {}\n\n", name));
+                    continue;
+                },
+                (HistoryItem::Scene(name), LogFormat::Text) => {
+                    transcript.push_str(&format!("\n--- Scene: {} ---\n", name));
+                    continue;
+                },
+                (HistoryItem::Scene(name), LogFormat::Markdown) => {
+                    transcript.push_str(&format!("### Scene: {}\n\n", name));
+                    continue;
+                },
+            };
+            if let Some(HistoryLine { speaker, text }) = line {
+                transcript.push_str(&match (speaker, format) {
+                    (Some(speaker), LogFormat::Text) => format!("{}: {}\n", speaker, text),
+                    (Some(speaker), LogFormat::Markdown) => format!("**{}**: {}\n\n", speaker, text),
+                    (None, LogFormat::Text) => format!("{}\n", text),
+                    (None, LogFormat::Markdown) => format!("*{}*\n\n", text),
+                });
+            }
+            if let Some(EnteredText { target, value }) = input {
+                transcript.push_str(&match format {
+                    LogFormat::Text => format!("> {} = {}\n", target, value),
+                    LogFormat::Markdown => format!("> `{}` = {}\n\n", target, value),
+                });
+            }
+            if let Some(choice) = choice {
+                transcript.push_str(&match format {
+                    LogFormat::Text => format!("> Choice: {}\n", choice),
+                    LogFormat::Markdown => format!("> **Choice**: {}\n\n", choice),
+                });
             }
         }
+        transcript
+    }
 
-        Ok(text)
+    /// Goes back to the line at `index` of the history, see [Self::can_rewind_to]
+    pub(crate) fn rewind_to(&mut self, index: usize) -> bool {
+        if !self.can_rewind_to(index) {
+            return false;
+        }
+        self.rewinding = self.history.len() - (index + 1);
+        self.blocking = false;
+        true
     }
 }
 
@@ -158,36 +380,195 @@ pub struct UserDefinedConstants {
     pub playername: String,
 }
 
-fn sabi_error_handler ( err: BevyError, ctx: ErrorContext ) {
-    panic!("Bevy error: {err:?}\nContext: {ctx:?}")
-}
-
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct ScriptId {
     pub chapter: String,
     pub act: String,
 }
 
+/// Starts a run of the given act, from its entrypoint unless a `scene` is provided.
+/// When starting later in the act, preceding stage commands are applied at once without showing any text.
+/// Built with [SabiStart::new], replacing the former `SabiStart(script)` tuple form.
 #[derive(Message)]
-pub struct SabiStart(pub ScriptId);
+pub struct SabiStart {
+    pub script: ScriptId,
+    pub scene: Option<String>,
+    /// Index of the first statement to run in the scene
+    pub statement: usize,
+}
+
+impl SabiStart {
+    pub fn new(script: ScriptId) -> Self {
+        Self { script, scene: None, statement: 0 }
+    }
+
+    /// Begins the run in `scene` rather than the act entrypoint
+    pub fn with_scene(mut self, scene: impl Into<String>) -> Self {
+        self.scene = Some(scene.into());
+        self
+    }
+
+    /// Begins the run at the given statement index of the scene, useful to test a line without clicking through the act
+    pub fn with_statement(mut self, statement: usize) -> Self {
+        self.statement = statement;
+        self
+    }
+}
 #[derive(Message)]
 pub struct SabiEnd;
+/// Freezes script execution, animations and text scrolling of the current run.
+#[derive(Message)]
+pub struct SabiPause;
+/// Resumes a run previously paused with [SabiPause].
+#[derive(Message)]
+pub struct SabiResume;
+/// Aborts the current run, cleaning up the stage and going back to [SabiState::Idle].
+#[derive(Message)]
+pub struct SabiStop;
+/// Jumps to the given scene of an act. If `scene` is [None], the act entrypoint is used.
+#[derive(Message)]
+pub struct SabiJump {
+    pub script: ScriptId,
+    pub scene: Option<String>,
+}
+
+/// Writes the log of the current run to `logs/<act>_<date>.<extension>` in the [data path](SabiSettings::data_path),
+/// writing [LogExported] once done.
+#[derive(Message, Debug, Clone, Default)]
+pub struct SabiExportLog {
+    pub format: LogFormat,
+}
+
+/* Lifecycle messages */
+/// Written every time an act starts running.
+#[derive(Message, Debug, Clone)]
+pub struct ActEntered(pub ScriptId);
+/// Written every time a scene starts running, including act entrypoints.
+#[derive(Message, Debug, Clone)]
+pub struct SceneEntered {
+    pub act: String,
+    pub scene: String,
+}
+/// Written when a dialogue line is shown in the textbox.
+#[derive(Message, Debug, Clone)]
+pub struct DialogueShown {
+    pub character: String,
+    pub text: String,
+}
+/// Written when the player confirms the text of an `input` statement.
+/// `target` is `playername` or the name of the variable.
+#[derive(Message, Debug, Clone)]
+pub struct TextInputConfirmed {
+    pub target: String,
+    pub value: String,
+}
+/// Written when the player picks an option of a choice. Scripts have no choice statement yet,
+/// so games showing their own choice menus write it for the other systems to react to.
+/// The picked option is kept in the history with the line shown at that moment, and appears in the transcripts.
+#[derive(Message, Debug, Clone)]
+pub struct ChoiceMade {
+    /// Index of the picked option, in the order the options were shown
+    pub index: usize,
+    /// Text of the picked option
+    pub text: String,
+}
+/// Written once a [SabiExportLog] is done, with the path of the transcript.
+#[derive(Message, Debug, Clone)]
+pub struct LogExported {
+    pub path: std::path::PathBuf,
+}
+/// Written when a character is spawned on stage.
+#[derive(Message, Debug, Clone)]
+pub struct CharacterSpawned {
+    pub character: String,
+}
+/// Written when a character leaves the stage.
+#[derive(Message, Debug, Clone)]
+pub struct CharacterDespawned {
+    pub character: String,
+}
+/// Written when the background changes. `background` is [None] when dissolving to an empty background.
+#[derive(Message, Debug, Clone)]
+pub struct BackgroundChanged {
+    pub background: Option<String>,
+}
 
+/// Main sabi plugin. Asset roots, fonts, layers, text speeds and the other [SabiSettings] are taken
+/// from the resource inserted before adding the plugin, the defaults being used when there is none.
+#[derive(Default)]
 pub struct SabiPlugin;
+
 impl Plugin for SabiPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<UserDefinedConstants>()
+        app.init_resource::<SabiSettings>()
+            .init_resource::<UserDefinedConstants>()
             .init_resource::<VisualNovelState>()
             .init_asset::<CharacterConfig>()
             .init_asset_loader::<CharacterJsonLoader>()
             .init_asset::<ast::Act>()
             .init_asset_loader::<PestLoader>()
-            .set_error_handler(sabi_error_handler)
+            .init_asset::<UiTheme>()
+            .init_asset_loader::<UiThemeLoader>()
             .add_plugins((
                 Compiler,
                 BackgroundController,
                 CharacterController,
-                ChatController
+                ChatController,
+                InputController,
+                ErrorController,
+                ConsoleController,
+                PreferencesController
             ));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn statement(index: usize, line: Option<HistoryLine>, input: Option<EnteredText>) -> HistoryItem {
+        let id = StatementId { act: String::from("act1"), scene: String::from("intro"), index };
+        HistoryItem::Statement { id, line, input, choice: None }
+    }
+
+    fn playthrough() -> VisualNovelState {
+        VisualNovelState {
+            history: vec![
+                HistoryItem::Act(String::from("act1")),
+                HistoryItem::Scene(String::from("intro")),
+                statement(0, None, None),
+                statement(1, Some(HistoryLine { speaker: None, text: String::from("It was raining.") }), None),
+                statement(2, Some(HistoryLine { speaker: Some(String::from("Nayu")), text: String::from("Who are you?") }), None),
+                statement(3, None, Some(EnteredText { target: String::from("playername"), value: String::from("Rin") })),
+                statement(4, Some(HistoryLine { speaker: Some(String::from("Nayu")), text: String::from("Tea or coffee?") }), None),
+            ],
+            ..default()
+        }
+    }
+
+    #[test]
+    fn transcript_as_text() {
+        assert_eq!(
+            playthrough().transcript(LogFormat::Text),
+            "\n=== Act: act1 ===\n\n--- Scene: intro ---\nIt was raining.\nNayu: Who are you?\n> playername = Rin\nNayu: Tea or coffee?\n"
+        );
+    }
+
+    #[test]
+    fn transcript_as_markdown() {
+        assert_eq!(
+            playthrough().transcript(LogFormat::Markdown),
+            "## Act: act1\n\n### Scene: intro\n\n*It was raining.*\n\n**Nayu**: Who are you?\n\n> `playername` = Rin\n\n**Nayu**: Tea or coffee?\n\n"
+        );
+    }
+
+    #[test]
+    fn transcript_with_choice() {
+        let mut state = playthrough();
+        state.record_choice("Tea");
+        assert!(state.transcript(LogFormat::Text).ends_with("Nayu: Tea or coffee?\n> Choice: Tea\n"));
+        assert!(state.transcript(LogFormat::Markdown).ends_with("**Nayu**: Tea or coffee?\n\n> **Choice**: Tea\n\n"));
+        // The choice does not add a history entry, rewinding counting the same steps
+        assert_eq!(state.history.len(), playthrough().history.len());
+    }
+}
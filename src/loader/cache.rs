@@ -0,0 +1,76 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::hash::Hash;
+use bevy::asset::{Asset, AssetServer, Handle, LoadState};
+
+use crate::error::SabiErrors;
+
+/// Lazily populated map of named asset handles.
+/// Assets are only loaded when requested, and the ones which failed to load are remembered
+/// so that they are not requested again.
+pub struct AssetCache<K, A: Asset> {
+    handles: HashMap<K, Handle<A>>,
+    failed: HashSet<K>,
+}
+
+impl<K, A: Asset> Default for AssetCache<K, A> {
+    fn default() -> Self {
+        Self {
+            handles: HashMap::new(),
+            failed: HashSet::new(),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone + Debug, A: Asset> AssetCache<K, A> {
+    /// Starts loading the asset identified by `key`, unless it was already requested
+    pub fn request(&mut self, key: &K, load: impl FnOnce() -> Handle<A>) {
+        if self.handles.contains_key(key) || self.failed.contains(key) {
+            return;
+        }
+        self.handles.insert(key.clone(), load());
+    }
+
+    pub fn get(&self, key: &K) -> Option<&Handle<A>> {
+        self.handles.get(key)
+    }
+
+    /// Releases the handles, and forgets the failures, of the keys for which `keep` returns false.
+    /// Assets still displayed stay alive through the handles of their entities.
+    pub fn retain(&mut self, mut keep: impl FnMut(&K) -> bool) {
+        self.handles.retain(|key, _| keep(key));
+        self.failed.retain(|key| keep(key));
+    }
+
+    /// Returns how many of the given keys finished loading, how many failed and how many were expected.
+    /// Failed assets are dropped from the cache and reported to [SabiErrors] once.
+    pub fn progress<'a>(&mut self, keys: impl Iterator<Item = &'a K>, asset_server: &AssetServer, errors: &mut SabiErrors) -> (usize, usize, usize)
+    where
+        K: 'a
+    {
+        let mut loaded = 0;
+        let mut failed = 0;
+        let mut total = 0;
+        for key in keys {
+            total += 1;
+            if self.failed.contains(key) {
+                failed += 1;
+                continue;
+            }
+            let Some(handle) = self.handles.get(key) else {
+                continue;
+            };
+            match asset_server.get_load_state(handle.id()) {
+                Some(LoadState::Loaded) => loaded += 1,
+                Some(LoadState::Failed(e)) => {
+                    errors.report(format!("Asset {:?} failed to load: {}", key, e));
+                    self.handles.remove(key);
+                    self.failed.insert(key.clone());
+                    failed += 1;
+                },
+                _ => {}
+            }
+        }
+        (loaded, failed, total)
+    }
+}
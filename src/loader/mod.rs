@@ -1,5 +1,9 @@
+pub(crate) mod cache;
 pub(crate) mod json;
 pub(crate) mod pest;
+pub(crate) mod theme;
 
+pub(crate) use cache::*;
 pub(crate) use json::*;
-pub(crate) use pest::*;
\ No newline at end of file
+pub(crate) use pest::*;
+pub(crate) use theme::*;
\ No newline at end of file
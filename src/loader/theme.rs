@@ -0,0 +1,39 @@
+use bevy::asset::AssetLoader;
+use thiserror::Error;
+
+use crate::chat::theme::UiTheme;
+
+#[derive(Debug, Error)]
+pub(crate) enum UiThemeError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("JSON parse error: {0}")]
+    Serde(#[from] serde_json::Error),
+}
+
+/// Custom asset loader to parse UI themes.
+#[derive(Default)]
+pub(crate) struct UiThemeLoader;
+impl AssetLoader for UiThemeLoader {
+    type Asset = UiTheme;
+    type Settings = ();
+    type Error = UiThemeError;
+
+    fn load(
+            &self,
+            reader: &mut dyn bevy::asset::io::Reader,
+            _settings: &Self::Settings,
+            _load_context: &mut bevy::asset::LoadContext,
+        ) -> impl bevy::tasks::ConditionalSendFuture<Output = std::result::Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let parsed: UiTheme = serde_json::from_slice(&bytes)?;
+            Ok(parsed)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["theme.json"]
+    }
+}
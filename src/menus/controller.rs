@@ -0,0 +1,185 @@
+use bevy::{asset::LoadState, prelude::*};
+use bevy_ui_widgets::Activate;
+
+use crate::{
+    SabiSettings, SabiStart, ScriptId,
+    chat::theme::UiTheme,
+    compiler::controller::SabiState,
+    menus::ui::title_screen,
+    preferences::{UserPreferences, ui::settings_menu}
+};
+
+/* Components */
+#[derive(Component)]
+pub(crate) struct TitleScreen;
+
+/* Resources */
+/// Configuration of the title screen, as given to the [SabiMenusPlugin]
+#[derive(Resource, Clone)]
+struct TitleMenu {
+    title: String,
+    new_game: ScriptId,
+    entries: Vec<TitleMenuEntry>,
+}
+/// Theme of the title screen, the one set in [SabiSettings] once loaded
+#[derive(Resource, Default)]
+struct TitleMenuTheme {
+    handle: Option<Handle<UiTheme>>,
+    theme: Option<UiTheme>,
+}
+
+/* Messages */
+/// Written when the player picks an entry of the title screen that sabi leaves to the game,
+/// namely [TitleMenuEntry::Continue], [TitleMenuEntry::Load] and [TitleMenuEntry::Gallery]
+#[derive(Message, Debug, Clone)]
+pub struct TitleMenuSelected(pub TitleMenuEntry);
+
+/* Custom types */
+/// Entries of the title screen, shown in the order they are given to [SabiMenusPlugin::entries]
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TitleMenuEntry {
+    /// Starts the act given to [SabiMenusPlugin::new]
+    NewGame,
+    Continue,
+    Load,
+    /// Opens the settings menu
+    Settings,
+    Gallery,
+    /// Closes the app
+    Quit,
+}
+
+impl TitleMenuEntry {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TitleMenuEntry::NewGame  => "New Game",
+            TitleMenuEntry::Continue => "Continue",
+            TitleMenuEntry::Load     => "Load",
+            TitleMenuEntry::Settings => "Settings",
+            TitleMenuEntry::Gallery  => "Gallery",
+            TitleMenuEntry::Quit     => "Quit",
+        }
+    }
+}
+
+/// Optional plugin showing a title screen whenever sabi is idle, to be added next to the [SabiPlugin](crate::SabiPlugin).
+/// The screen uses the `title` section of the theme set with [SabiSettings::theme](crate::SabiSettings::theme).
+pub struct SabiMenusPlugin {
+    menu: TitleMenu,
+}
+
+impl SabiMenusPlugin {
+    /// Title screen whose New Game entry starts the given act
+    pub fn new(new_game: ScriptId) -> Self {
+        Self {
+            menu: TitleMenu {
+                title: String::new(),
+                new_game,
+                entries: vec![
+                    TitleMenuEntry::NewGame,
+                    TitleMenuEntry::Continue,
+                    TitleMenuEntry::Load,
+                    TitleMenuEntry::Settings,
+                    TitleMenuEntry::Gallery,
+                    TitleMenuEntry::Quit,
+                ],
+            },
+        }
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.menu.title = title.into();
+        self
+    }
+
+    pub fn entries(mut self, entries: impl IntoIterator<Item = TitleMenuEntry>) -> Self {
+        self.menu.entries = entries.into_iter().collect();
+        self
+    }
+}
+
+impl Plugin for SabiMenusPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.menu.clone())
+            .init_resource::<TitleMenuTheme>()
+            .add_message::<TitleMenuSelected>()
+            .add_systems(Startup, request_theme)
+            .add_systems(Update, spawn_title_screen.run_if(in_state(SabiState::Idle)))
+            .add_observer(entry_clicked);
+    }
+}
+
+fn request_theme(
+    asset_server: Res<AssetServer>,
+    settings: Res<SabiSettings>,
+    mut title_theme: ResMut<TitleMenuTheme>,
+) {
+    title_theme.handle = settings.theme.as_ref()
+        .map(|name| asset_server.load(format!("{}/{}.theme.json", settings.ui_path, name)));
+    if title_theme.handle.is_none() {
+        title_theme.theme = Some(UiTheme::default());
+    }
+}
+/// Spawns the title screen once its theme is loaded, falling back to the default theme when it cannot be
+fn spawn_title_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<SabiSettings>,
+    menu: Res<TitleMenu>,
+    mut title_theme: ResMut<TitleMenuTheme>,
+    themes: Res<Assets<UiTheme>>,
+    q_title_screen: Query<(), With<TitleScreen>>,
+) {
+    if !q_title_screen.is_empty() {
+        return;
+    }
+    if title_theme.theme.is_none() && let Some(handle) = &title_theme.handle {
+        if let Some(theme) = themes.get(handle) {
+            title_theme.theme = Some(theme.clone());
+        } else if matches!(asset_server.load_state(handle), LoadState::Failed(_)) {
+            warn!("Failed to load UI theme for the title screen, using the default one");
+            title_theme.theme = Some(UiTheme::default());
+        }
+    }
+    let Some(theme) = &title_theme.theme else {
+        return;
+    };
+    commands.spawn(title_screen(&menu.title, &menu.entries, &asset_server, &settings, theme));
+}
+fn entry_clicked(
+    trigger: On<Activate>,
+    mut commands: Commands,
+    q_entries: Query<&TitleMenuEntry>,
+    menu: Res<TitleMenu>,
+    sabi_state: Res<State<SabiState>>,
+    title_theme: Res<TitleMenuTheme>,
+    preferences: Res<UserPreferences>,
+    asset_server: Res<AssetServer>,
+    settings: Res<SabiSettings>,
+    mut start_writer: MessageWriter<SabiStart>,
+    mut selected_writer: MessageWriter<TitleMenuSelected>,
+    mut exit_writer: MessageWriter<AppExit>,
+) {
+    let Ok(entry) = q_entries.get(trigger.entity) else {
+        return;
+    };
+    if *sabi_state != SabiState::Idle {
+        return;
+    }
+    match entry {
+        TitleMenuEntry::NewGame => {
+            info!("[ Title screen: new game ]");
+            start_writer.write(SabiStart::new(menu.new_game.clone()));
+        },
+        TitleMenuEntry::Settings => {
+            let theme = title_theme.theme.clone().unwrap_or_default();
+            commands.spawn((settings_menu(&preferences, &asset_server, &settings, &theme), DespawnOnExit(SabiState::Idle)));
+        },
+        TitleMenuEntry::Quit => {
+            exit_writer.write(AppExit::Success);
+        },
+        other => {
+            selected_writer.write(TitleMenuSelected(*other));
+        },
+    }
+}
@@ -0,0 +1,6 @@
+pub(crate) mod controller;
+mod ui;
+
+pub use controller::SabiMenusPlugin;
+pub use controller::TitleMenuEntry;
+pub use controller::TitleMenuSelected;
@@ -0,0 +1,84 @@
+use bevy::prelude::*;
+
+use crate::{
+    SabiSettings, SabiState,
+    chat::{button_node, button_radius, theme::UiTheme},
+    menus::controller::{TitleMenuEntry, TitleScreen}
+};
+
+fn entry(
+    entry: TitleMenuEntry,
+    asset_server: &AssetServer,
+    settings: &SabiSettings,
+    theme: &UiTheme,
+) -> impl Bundle {
+    let buttons = &theme.title.buttons;
+    (
+        Node {
+            width: theme.title.entry_width.0,
+            right: Val::Auto,
+            top: Val::Auto,
+            ..button_node(buttons, PositionType::Relative)
+        },
+        BorderColor::all(buttons.border.0),
+        button_radius(buttons),
+        BackgroundColor(buttons.background.0),
+        Button,
+        children![(
+            Text::new(entry.label()),
+            buttons.text.font(asset_server, settings),
+            TextColor(buttons.text.color.0),
+            TextShadow::default()
+        )],
+        entry,
+    )
+}
+
+pub(in crate::menus) fn title_screen(
+    title: &str,
+    entries: &[TitleMenuEntry],
+    asset_server: &AssetServer,
+    settings: &SabiSettings,
+    theme: &UiTheme,
+) -> impl Bundle {
+    let image = theme.title.image.as_ref()
+        .map(|image| ImageNode::new(asset_server.load(format!("{}/{}.{}", settings.ui_path, image, settings.image_extension))))
+        .unwrap_or_default();
+    let entries = entries.iter()
+        .map(|e| entry(*e, asset_server, settings, theme))
+        .collect::<Vec<_>>();
+    (
+        Node {
+            position_type: PositionType::Absolute,
+            width: percent(100.),
+            height: percent(100.),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::SpaceEvenly,
+            padding: theme.title.padding.0,
+            ..default()
+        },
+        BackgroundColor(theme.title.background.0),
+        image,
+        ZIndex(settings.layers.ui),
+        TitleScreen,
+        DespawnOnExit(SabiState::Idle),
+        children![
+            (
+                Text::new(title),
+                theme.title.title.font(asset_server, settings),
+                TextColor(theme.title.title.color.0),
+                TextShadow::default(),
+            ),
+            (
+                Node {
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    row_gap: theme.title.entry_spacing.0,
+                    ..default()
+                },
+                Children::spawn(SpawnIter(entries.into_iter())),
+            )
+        ]
+    )
+}
@@ -0,0 +1,284 @@
+use std::fs;
+
+use anyhow::Context;
+use bevy::{
+    audio::{GlobalVolume, Volume},
+    prelude::*,
+    window::{MonitorSelection, PrimaryWindow, VideoModeSelection, WindowMode},
+};
+use bevy_ui_widgets::Activate;
+use serde::{Deserialize, Serialize};
+
+use crate::{SabiSettings, input::{SabiAction, SabiActionMessage}, preferences::ui::SettingsValue};
+
+const PREFERENCES_FILE: &str = "preferences.json";
+
+/* Components */
+#[derive(Component)]
+pub(crate) struct SettingsMenu;
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SettingsMenuButton {
+    /// Steps the option back (-1) or forward (1)
+    Change(PreferenceOption, i32),
+    Close,
+}
+
+/* Resources */
+/// Preferences of the player, changed from the settings menu and stored in `preferences.json`
+/// in the [data path](SabiSettings::data_path). Changing them from the game applies them too, but does not save them.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserPreferences {
+    /// Scrolling speed of the dialogue, in characters per second
+    pub dialogue_speed: f32,
+    /// Scrolling speed of the infotexts, in characters per second
+    pub infotext_speed: f32,
+    /// Seconds the auto mode waits once a line is fully shown
+    pub auto_delay: f32,
+    /// Whether skipping goes through lines the player has never read
+    pub skip_unread: bool,
+    pub window_mode: WindowModePreference,
+    /// Volume of every sound, from 0 to 1, applied to the bevy [GlobalVolume]
+    pub master_volume: f32,
+    /// Channel volumes from 0 to 1, left to the game as sabi does not play audio itself
+    pub music_volume: f32,
+    pub sound_volume: f32,
+    pub voice_volume: f32,
+    /// Scale of the whole UI, applied to the bevy [UiScale]
+    pub ui_scale: f32,
+}
+
+impl Default for UserPreferences {
+    fn default() -> Self {
+        Self::new(&SabiSettings::default())
+    }
+}
+
+impl UserPreferences {
+    fn new(settings: &SabiSettings) -> Self {
+        Self {
+            dialogue_speed: settings.text_speeds.dialogue,
+            infotext_speed: settings.text_speeds.infotext,
+            auto_delay: 2.,
+            skip_unread: false,
+            window_mode: WindowModePreference::Windowed,
+            master_volume: 1.,
+            music_volume: 1.,
+            sound_volume: 1.,
+            voice_volume: 1.,
+            ui_scale: 1.,
+        }
+    }
+
+    /// Reads the stored preferences, starting from the speeds of the [SabiSettings] when there are none
+    fn load(settings: &SabiSettings) -> Self {
+        let Some(path) = settings.data_path().map(|dir| dir.join(PREFERENCES_FILE)) else {
+            return Self::new(settings);
+        };
+        let Ok(content) = fs::read_to_string(&path) else {
+            return Self::new(settings);
+        };
+        match serde_json::from_str(&content) {
+            Ok(preferences) => {
+                info!("[ Loaded preferences from {} ]", path.display());
+                preferences
+            },
+            Err(e) => {
+                warn!("Invalid preferences file {}, using the defaults: {}", path.display(), e);
+                Self::new(settings)
+            }
+        }
+    }
+
+    fn save(&self, settings: &SabiSettings) -> anyhow::Result<()> {
+        let dir = settings.data_path().context("No user data directory to store the preferences in")?;
+        fs::create_dir_all(&dir).with_context(|| format!("Could not create {}", dir.display()))?;
+        let path = dir.join(PREFERENCES_FILE);
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(&path, content).with_context(|| format!("Could not write {}", path.display()))
+    }
+}
+
+/* Custom types */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowModePreference {
+    #[default]
+    Windowed,
+    Borderless,
+    Fullscreen,
+}
+
+impl WindowModePreference {
+    fn mode(&self) -> WindowMode {
+        match self {
+            WindowModePreference::Windowed => WindowMode::Windowed,
+            WindowModePreference::Borderless => WindowMode::BorderlessFullscreen(MonitorSelection::Current),
+            WindowModePreference::Fullscreen => WindowMode::Fullscreen(MonitorSelection::Current, VideoModeSelection::Current),
+        }
+    }
+}
+/// Line of the settings menu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PreferenceOption {
+    DialogueSpeed,
+    InfotextSpeed,
+    AutoDelay,
+    SkipUnread,
+    WindowMode,
+    MasterVolume,
+    MusicVolume,
+    SoundVolume,
+    VoiceVolume,
+    UiScale,
+}
+
+impl PreferenceOption {
+    pub const ALL: [PreferenceOption; 10] = [
+        PreferenceOption::DialogueSpeed,
+        PreferenceOption::InfotextSpeed,
+        PreferenceOption::AutoDelay,
+        PreferenceOption::SkipUnread,
+        PreferenceOption::WindowMode,
+        PreferenceOption::MasterVolume,
+        PreferenceOption::MusicVolume,
+        PreferenceOption::SoundVolume,
+        PreferenceOption::VoiceVolume,
+        PreferenceOption::UiScale,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PreferenceOption::DialogueSpeed => "Text speed",
+            PreferenceOption::InfotextSpeed => "Narration speed",
+            PreferenceOption::AutoDelay     => "Auto delay",
+            PreferenceOption::SkipUnread    => "Skip unread text",
+            PreferenceOption::WindowMode    => "Window",
+            PreferenceOption::MasterVolume  => "Master volume",
+            PreferenceOption::MusicVolume   => "Music volume",
+            PreferenceOption::SoundVolume   => "Sound volume",
+            PreferenceOption::VoiceVolume   => "Voice volume",
+            PreferenceOption::UiScale       => "UI scale",
+        }
+    }
+
+    pub fn value(&self, preferences: &UserPreferences) -> String {
+        let percent = |volume: f32| format!("{}%", (volume * 100.).round());
+        match self {
+            PreferenceOption::DialogueSpeed => format!("{} cps", preferences.dialogue_speed),
+            PreferenceOption::InfotextSpeed => format!("{} cps", preferences.infotext_speed),
+            PreferenceOption::AutoDelay     => format!("{:.1} s", preferences.auto_delay),
+            PreferenceOption::SkipUnread    => String::from(if preferences.skip_unread { "On" } else { "Off" }),
+            PreferenceOption::WindowMode    => format!("{:?}", preferences.window_mode),
+            PreferenceOption::MasterVolume  => percent(preferences.master_volume),
+            PreferenceOption::MusicVolume   => percent(preferences.music_volume),
+            PreferenceOption::SoundVolume   => percent(preferences.sound_volume),
+            PreferenceOption::VoiceVolume   => percent(preferences.voice_volume),
+            PreferenceOption::UiScale       => format!("{:.1}x", preferences.ui_scale),
+        }
+    }
+
+    /// Moves the option `steps` steps, clamping numbers and cycling through choices
+    fn change(&self, preferences: &mut UserPreferences, steps: i32) {
+        let step = |value: &mut f32, by: f32, min: f32, max: f32| {
+            *value = ((*value + by * steps as f32) / by).round() * by;
+            *value = value.clamp(min, max);
+        };
+        match self {
+            PreferenceOption::DialogueSpeed => step(&mut preferences.dialogue_speed, 5., 5., 200.),
+            PreferenceOption::InfotextSpeed => step(&mut preferences.infotext_speed, 5., 5., 200.),
+            PreferenceOption::AutoDelay     => step(&mut preferences.auto_delay, 0.5, 0.5, 10.),
+            PreferenceOption::SkipUnread    => preferences.skip_unread = !preferences.skip_unread,
+            PreferenceOption::WindowMode    => {
+                let modes = [WindowModePreference::Windowed, WindowModePreference::Borderless, WindowModePreference::Fullscreen];
+                let current = modes.iter().position(|m| *m == preferences.window_mode).unwrap_or_default() as i32;
+                preferences.window_mode = modes[(current + steps).rem_euclid(modes.len() as i32) as usize];
+            },
+            PreferenceOption::MasterVolume  => step(&mut preferences.master_volume, 0.1, 0., 1.),
+            PreferenceOption::MusicVolume   => step(&mut preferences.music_volume, 0.1, 0., 1.),
+            PreferenceOption::SoundVolume   => step(&mut preferences.sound_volume, 0.1, 0., 1.),
+            PreferenceOption::VoiceVolume   => step(&mut preferences.voice_volume, 0.1, 0., 1.),
+            PreferenceOption::UiScale       => step(&mut preferences.ui_scale, 0.1, 0.5, 2.),
+        }
+    }
+}
+
+pub(crate) struct PreferencesController;
+impl Plugin for PreferencesController {
+    fn build(&self, app: &mut App) {
+        let preferences = match app.world().get_resource::<SabiSettings>() {
+            Some(settings) => UserPreferences::load(settings),
+            None => UserPreferences::load(&SabiSettings::default()),
+        };
+        app.insert_resource(preferences)
+            .add_systems(Update, apply_preferences.run_if(resource_changed::<UserPreferences>))
+            .add_systems(Update, close_settings_menu.run_if(not(settings_closed)))
+            .add_observer(settings_button_clicked);
+    }
+}
+
+/// Whether the settings menu is closed, the player actions being meant for it otherwise
+pub(crate) fn settings_closed(q_menu: Query<(), With<SettingsMenu>>) -> bool {
+    q_menu.is_empty()
+}
+/// Closes the settings menu on [SabiAction::Menu]
+pub(crate) fn close_settings_menu(
+    mut commands: Commands,
+    mut action_messages: MessageReader<SabiActionMessage>,
+    q_menu: Query<Entity, With<SettingsMenu>>,
+) {
+    if !action_messages.read().any(|msg| msg.0 == SabiAction::Menu) {
+        return;
+    }
+    for menu in q_menu.iter() {
+        commands.entity(menu).despawn();
+    }
+}
+fn apply_preferences(
+    preferences: Res<UserPreferences>,
+    mut settings: ResMut<SabiSettings>,
+    ui_scale: Option<ResMut<UiScale>>,
+    global_volume: Option<ResMut<GlobalVolume>>,
+    mut q_window: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    settings.text_speeds.dialogue = preferences.dialogue_speed;
+    settings.text_speeds.infotext = preferences.infotext_speed;
+    if let Some(mut ui_scale) = ui_scale {
+        ui_scale.0 = preferences.ui_scale;
+    }
+    if let Some(mut global_volume) = global_volume {
+        global_volume.volume = Volume::Linear(preferences.master_volume);
+    }
+    if let Ok(mut window) = q_window.single_mut() && window.mode != preferences.window_mode.mode() {
+        window.mode = preferences.window_mode.mode();
+    }
+}
+fn settings_button_clicked(
+    trigger: On<Activate>,
+    mut commands: Commands,
+    q_buttons: Query<&SettingsMenuButton>,
+    mut q_values: Query<(&mut Text, &SettingsValue)>,
+    q_menu: Query<Entity, With<SettingsMenu>>,
+    mut preferences: ResMut<UserPreferences>,
+    settings: Res<SabiSettings>,
+) {
+    let Ok(button) = q_buttons.get(trigger.entity) else {
+        return;
+    };
+    match button {
+        SettingsMenuButton::Change(option, steps) => {
+            option.change(&mut preferences, *steps);
+            for (mut text, value) in q_values.iter_mut().filter(|(_, value)| value.0 == *option) {
+                text.0 = value.0.value(&preferences);
+            }
+            if let Err(e) = preferences.save(&settings) {
+                warn!("Failed to save preferences: {:#}", e);
+            }
+        },
+        SettingsMenuButton::Close => {
+            for menu in q_menu.iter() {
+                commands.entity(menu).despawn();
+            }
+        },
+    }
+}
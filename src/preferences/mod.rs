@@ -0,0 +1,7 @@
+pub(crate) mod controller;
+pub(crate) mod ui;
+
+pub(crate) use controller::PreferencesController;
+pub(crate) use controller::{SettingsMenu, close_settings_menu, settings_closed};
+pub use controller::UserPreferences;
+pub use controller::WindowModePreference;
@@ -0,0 +1,126 @@
+use bevy::prelude::*;
+
+use crate::{
+    SabiSettings,
+    chat::{button_node, button_radius, theme::{ButtonTheme, UiTheme}},
+    preferences::controller::{PreferenceOption, SettingsMenu, SettingsMenuButton, UserPreferences}
+};
+
+/* Components */
+/// Text showing the current value of an option
+#[derive(Component)]
+pub(crate) struct SettingsValue(pub PreferenceOption);
+
+fn settings_button(
+    label: &str,
+    action: SettingsMenuButton,
+    asset_server: &AssetServer,
+    settings: &SabiSettings,
+    theme: &ButtonTheme,
+) -> impl Bundle {
+    (
+        Node {
+            min_width: px(36),
+            right: Val::Auto,
+            top: Val::Auto,
+            ..button_node(theme, PositionType::Relative)
+        },
+        BorderColor::all(theme.border.0),
+        button_radius(theme),
+        BackgroundColor(theme.background.0),
+        action,
+        Button,
+        children![(
+            Text::new(label),
+            theme.text.font(asset_server, settings),
+            TextColor(theme.text.color.0),
+        )]
+    )
+}
+
+fn option_row(
+    option: PreferenceOption,
+    preferences: &UserPreferences,
+    asset_server: &AssetServer,
+    settings: &SabiSettings,
+    theme: &UiTheme,
+) -> impl Bundle {
+    let text_font = theme.settings.text.font(asset_server, settings);
+    let text_color = TextColor(theme.settings.text.color.0);
+    (
+        Node {
+            width: percent(100),
+            align_items: AlignItems::Center,
+            column_gap: px(12),
+            ..default()
+        },
+        children![
+            (
+                Node { flex_grow: 1., ..default() },
+                children![(Text::new(option.label()), text_font.clone(), text_color)]
+            ),
+            settings_button("<", SettingsMenuButton::Change(option, -1), asset_server, settings, &theme.buttons),
+            (
+                Node { width: px(130), justify_content: JustifyContent::Center, ..default() },
+                children![(Text::new(option.value(preferences)), text_font, text_color, SettingsValue(option))]
+            ),
+            settings_button(">", SettingsMenuButton::Change(option, 1), asset_server, settings, &theme.buttons),
+        ]
+    )
+}
+
+pub(crate) fn settings_menu(
+    preferences: &UserPreferences,
+    asset_server: &AssetServer,
+    settings: &SabiSettings,
+    theme: &UiTheme,
+) -> impl Bundle {
+    let rows = PreferenceOption::ALL.iter()
+        .map(|option| option_row(*option, preferences, asset_server, settings, theme))
+        .collect::<Vec<_>>();
+    (
+        // Backdrop catching the clicks meant for the screen behind
+        Node {
+            width: percent(100),
+            height: percent(100),
+            position_type: PositionType::Absolute,
+            top: px(0),
+            left: px(0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        BackgroundColor(theme.settings.backdrop.0),
+        ZIndex(settings.layers.ui + 1),
+        SettingsMenu,
+        children![(
+            Node {
+                width: theme.settings.width.0,
+                padding: theme.settings.padding.0,
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: theme.settings.row_spacing.0,
+                ..default()
+            },
+            BackgroundColor(theme.settings.background.0),
+            BorderRadius::all(px(8)),
+            children![
+                (
+                    Text::new("Settings"),
+                    theme.settings.title.font(asset_server, settings),
+                    TextColor(theme.settings.title.color.0),
+                ),
+                (
+                    Node {
+                        width: percent(100),
+                        flex_direction: FlexDirection::Column,
+                        row_gap: theme.settings.row_spacing.0,
+                        ..default()
+                    },
+                    Children::spawn(SpawnIter(rows.into_iter())),
+                ),
+                settings_button("Close", SettingsMenuButton::Close, asset_server, settings, &theme.buttons),
+            ]
+        )]
+    )
+}
@@ -0,0 +1,228 @@
+use std::{collections::HashMap, env, path::PathBuf};
+
+use bevy::prelude::*;
+
+use crate::ErrorPolicy;
+
+/// Ordering of the layers sabi spawns under the ui root, expressed as [ZIndex] values.
+#[derive(Debug, Clone)]
+pub struct SabiLayers {
+    pub background: i32,
+    pub characters: i32,
+    pub infotext_active: i32,
+    pub infotext_inactive: i32,
+    pub ui: i32,
+    pub loading: i32,
+}
+
+impl Default for SabiLayers {
+    fn default() -> Self {
+        Self {
+            background: 1,
+            characters: 3,
+            infotext_active: 4,
+            infotext_inactive: -1,
+            ui: 5,
+            loading: 100,
+        }
+    }
+}
+
+/// Fonts used by the `[b]` and `[i]` text tags, the regular one being [SabiSettings::font].
+#[derive(Debug, Clone)]
+pub struct MarkupFonts {
+    pub bold: String,
+    pub italic: String,
+    pub bold_italic: String,
+}
+
+impl Default for MarkupFonts {
+    fn default() -> Self {
+        Self {
+            bold: "sabi/fonts/BOLD.ttf".into(),
+            // The bundled fonts have no regular weight italic
+            italic: "sabi/fonts/BLACKITALIC.ttf".into(),
+            bold_italic: "sabi/fonts/BOLDITALIC.ttf".into(),
+        }
+    }
+}
+
+/// Scrolling speeds of the text, in characters per second.
+/// Characters are counted as they are displayed, so an accented letter or an emoji takes a single step.
+#[derive(Debug, Clone)]
+pub struct TextSpeeds {
+    pub dialogue: f32,
+    pub infotext: f32,
+    /// Extra seconds waited after a punctuation mark, once per run of marks
+    pub punctuation_pauses: HashMap<char, f32>,
+}
+
+impl Default for TextSpeeds {
+    fn default() -> Self {
+        let stops = ['.', '!', '?', '…', '。', '！', '？'].map(|c| (c, 0.3));
+        let commas = [',', ';', ':', '、', '，', '；', '：'].map(|c| (c, 0.12));
+        Self {
+            dialogue: 50.,
+            infotext: 25.,
+            punctuation_pauses: stops.into_iter().chain(commas).collect(),
+        }
+    }
+}
+
+/// Default durations of the stage animations, in seconds.
+/// Scripts can override them with `over <duration>`.
+#[derive(Debug, Clone)]
+pub struct AnimationDurations {
+    pub fade: f32,
+    pub movement: f32,
+    pub dissolve: f32,
+    pub slide: f32,
+}
+
+impl Default for AnimationDurations {
+    fn default() -> Self {
+        Self {
+            fade: 1.,
+            movement: 1.,
+            dissolve: 2.,
+            slide: 3.,
+        }
+    }
+}
+
+/// Resource holding asset locations, layer ordering, text and animation speeds and error policy used by sabi.
+/// Asset paths are relative to the bevy assets root.
+#[derive(Resource, Debug, Clone)]
+pub struct SabiSettings {
+    pub scripts_path: String,
+    pub characters_path: String,
+    pub backgrounds_path: String,
+    pub ui_path: String,
+    pub font: String,
+    pub markup_fonts: MarkupFonts,
+    /// Name of the UI theme loaded from `<ui_path>/<name>.theme.json`, the built-in look when [None]
+    pub theme: Option<String>,
+    /// Extension of background, character and GUI images
+    pub image_extension: String,
+    pub layers: SabiLayers,
+    pub text_speeds: TextSpeeds,
+    pub animation_durations: AnimationDurations,
+    pub error_policy: ErrorPolicy,
+    /// Key toggling the developer console, [None] disables it.
+    /// Defaults to F12 in debug builds only
+    pub console_key: Option<KeyCode>,
+    /// Folder of the user data directory holding the player preferences, read lines and logs.
+    /// [None] takes the name of the game executable, so that games never share their data.
+    pub data_folder: Option<String>,
+}
+
+impl Default for SabiSettings {
+    fn default() -> Self {
+        Self {
+            scripts_path: "sabi/acts".into(),
+            characters_path: "sabi/characters".into(),
+            backgrounds_path: "sabi/backgrounds".into(),
+            ui_path: "sabi/ui".into(),
+            font: "sabi/fonts/ALLER.ttf".into(),
+            markup_fonts: MarkupFonts::default(),
+            theme: None,
+            image_extension: "png".into(),
+            layers: SabiLayers::default(),
+            text_speeds: TextSpeeds::default(),
+            animation_durations: AnimationDurations::default(),
+            error_policy: ErrorPolicy::default(),
+            console_key: if cfg!(debug_assertions) { Some(KeyCode::F12) } else { None },
+            data_folder: None,
+        }
+    }
+}
+
+impl SabiSettings {
+    pub fn scripts_path(mut self, path: impl Into<String>) -> Self {
+        self.scripts_path = path.into();
+        self
+    }
+
+    pub fn characters_path(mut self, path: impl Into<String>) -> Self {
+        self.characters_path = path.into();
+        self
+    }
+
+    pub fn backgrounds_path(mut self, path: impl Into<String>) -> Self {
+        self.backgrounds_path = path.into();
+        self
+    }
+
+    pub fn ui_path(mut self, path: impl Into<String>) -> Self {
+        self.ui_path = path.into();
+        self
+    }
+
+    pub fn font(mut self, path: impl Into<String>) -> Self {
+        self.font = path.into();
+        self
+    }
+
+    pub fn image_extension(mut self, extension: impl Into<String>) -> Self {
+        self.image_extension = extension.into();
+        self
+    }
+
+    pub fn layers(mut self, layers: SabiLayers) -> Self {
+        self.layers = layers;
+        self
+    }
+
+    pub fn text_speeds(mut self, text_speeds: TextSpeeds) -> Self {
+        self.text_speeds = text_speeds;
+        self
+    }
+
+    pub fn theme(mut self, name: impl Into<String>) -> Self {
+        self.theme = Some(name.into());
+        self
+    }
+
+    pub fn markup_fonts(mut self, markup_fonts: MarkupFonts) -> Self {
+        self.markup_fonts = markup_fonts;
+        self
+    }
+
+    pub fn animation_durations(mut self, animation_durations: AnimationDurations) -> Self {
+        self.animation_durations = animation_durations;
+        self
+    }
+
+    pub fn error_policy(mut self, error_policy: ErrorPolicy) -> Self {
+        self.error_policy = error_policy;
+        self
+    }
+
+    pub fn console_key(mut self, key: Option<KeyCode>) -> Self {
+        self.console_key = key;
+        self
+    }
+
+    pub fn data_folder(mut self, folder: impl Into<String>) -> Self {
+        self.data_folder = Some(folder.into());
+        self
+    }
+
+    /// Directory of the player data, [None] when the platform has no user data directory
+    /// or the folder cannot be named, with no `data_folder` nor executable name
+    pub fn data_path(&self) -> Option<PathBuf> {
+        let folder = match &self.data_folder {
+            Some(folder) => folder.clone(),
+            None => env::current_exe().ok()?.file_stem()?.to_str()?.to_owned(),
+        };
+        let user_data = if cfg!(target_os = "windows") {
+            env::var_os("APPDATA").map(PathBuf::from)
+        } else if cfg!(target_os = "macos") {
+            env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+        } else {
+            env::var_os("XDG_DATA_HOME").map(PathBuf::from)
+                .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        };
+        user_data.map(|dir| dir.join(folder))
+    }
+}
@@ -0,0 +1,103 @@
+use bevy::prelude::*;
+
+use crate::VisualNovelState;
+
+/// Duration and easing curve of an animated stage command, written in scripts as `over 0.8s ease-out`.
+/// When `duration` is [None], the default duration of the animation from [AnimationDurations](crate::AnimationDurations) is used.
+/// Commands marked `nowait` let the script go on while they animate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timing {
+    pub duration: Option<f32>,
+    pub easing: EaseFunction,
+    pub nowait: bool,
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Self {
+            duration: None,
+            easing: EaseFunction::Linear,
+            nowait: false,
+        }
+    }
+}
+
+/// Progress of a time-based animation, independent from the frame rate
+#[derive(Debug, Clone)]
+pub struct Tween {
+    duration: f32,
+    easing: EaseFunction,
+    elapsed: f32,
+}
+
+impl Tween {
+    pub fn new(timing: Timing, default_duration: f32) -> Self {
+        Self {
+            duration: timing.duration.unwrap_or(default_duration).max(0.),
+            easing: timing.easing,
+            elapsed: 0.,
+        }
+    }
+
+    /// Advances the animation by `delta` seconds, returning its eased progress
+    pub fn tick(&mut self, delta: f32) -> f32 {
+        self.elapsed += delta;
+        self.progress()
+    }
+
+    /// Eased progress of the animation, from 0 to 1
+    pub fn progress(&self) -> f32 {
+        let t = if self.duration > 0. { (self.elapsed / self.duration).min(1.) } else { 1. };
+        self.easing.sample_clamped(t)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    /// Jumps to the end of the animation, which is applied on the next tick
+    pub fn finish(&mut self) {
+        self.elapsed = self.duration;
+    }
+}
+
+/* Resources */
+/// Amount of running animations the script is waiting for.
+/// Animations started together, e.g. with `with`, unblock the script only once the last one ends.
+#[derive(Resource, Default)]
+pub(crate) struct BlockingAnimations(usize);
+
+impl BlockingAnimations {
+    pub fn start(&mut self, game_state: &mut VisualNovelState) {
+        self.0 += 1;
+        game_state.blocking = true;
+    }
+
+    pub fn end(&mut self, game_state: &mut VisualNovelState) {
+        self.0 = self.0.saturating_sub(1);
+        if self.0 == 0 {
+            game_state.blocking = false;
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.0 = 0;
+    }
+}
+
+/* Messages */
+/// Written when the player advances during a blocking animation: every running [Tween] jumps to its end state,
+/// the same way the first click on the textbox completes the text scrolling.
+#[derive(Message)]
+pub(crate) struct CompleteAnimationsMessage;
+
+/// Parses the easing names accepted by scripts
+pub(crate) fn easing_from_name(name: &str) -> Option<EaseFunction> {
+    match name {
+        "linear" => Some(EaseFunction::Linear),
+        "ease-in" => Some(EaseFunction::CubicIn),
+        "ease-out" => Some(EaseFunction::CubicOut),
+        "ease-in-out" => Some(EaseFunction::CubicInOut),
+        _ => None,
+    }
+}